@@ -1,5 +1,8 @@
 // src/cli.rs
 use clap::{Parser, Subcommand};
+use helix::oligo::SeedPolicy;
+use helix::manifest::Codec;
+use helix::fountain::MAX_OVERHEAD;
 
 #[derive(Parser)]
 #[command(name = "helix", author, version, about, long_about = None)]
@@ -54,30 +57,66 @@ pub enum Commands {
         #[arg(long, value_name = "PASSWORD")]
         password: Option<String>,
 
-        /// Number of data shards for Reed-Solomon (N)
-        #[arg(long, default_value_t = 10, value_name = "N")]
-        data: usize,
+        /// Number of data shards for Reed-Solomon (N, default 10), or source symbols
+        /// per block for the fountain codec (default 100)
+        #[arg(long, value_name = "N")]
+        data: Option<usize>,
 
-        /// Number of parity shards for redundancy (K)
-        #[arg(long, default_value_t = 5, value_name = "K")]
-        parity: usize,
+        /// Number of parity shards for redundancy (K, Reed-Solomon only, default 5).
+        /// Rejected under --codec fountain; use --overhead instead.
+        #[arg(long, value_name = "K")]
+        parity: Option<usize>,
+
+        /// Block erasure code: `rs` (fixed N+K Reed-Solomon) or `fountain` (rateless LT
+        /// droplets; any sufficient subset rebuilds a block). Recorded in the archive.
+        #[arg(long, default_value_t = Codec::ReedSolomon, value_name = "CODEC")]
+        codec: Codec,
+
+        /// Fountain droplets emitted per source symbol, e.g. `1.5x` (fountain codec only,
+        /// default 1.5x). Replaces --parity.
+        #[arg(long, value_name = "RATIO", value_parser = parse_overhead)]
+        overhead: Option<f64>,
 
         /// Ignore synthesis safety warnings and force compilation
         #[arg(long)]
         force: bool,
+
+        /// Partition blocks across T tubes, each with its own orthogonal primer pair.
+        /// Emits one FASTA per tube plus a `.tubes.json` map.
+        #[arg(long, default_value_t = 1, value_name = "T", value_parser = clap::value_parser!(u16).range(1..=64))]
+        tubes: u16,
+
+        /// Trellis seed policy: `chained` (Address/Payload seeded from the preceding region)
+        /// or `fixed:XY` (fixed Address seed X and Payload seed Y). Recorded in the archive.
+        #[arg(long, default_value_t = SeedPolicy::Chained, value_name = "POLICY")]
+        seed: SeedPolicy,
+
+        /// Emit every strand N times (shuffled within each block), modelling physical
+        /// synthesis redundancy. The decoder uses whichever copies survive.
+        #[arg(long, default_value_t = 1, value_name = "N", value_parser = clap::value_parser!(u16).range(1..=100))]
+        copies: u16,
     },
 
     /// Restore, Decrypt, and Decompress a file from a DNA archive.
     #[command(visible_alias = "dec")]
     Restore {
-        /// Input DNA FASTA file (the "Soup")
-        #[arg(value_name = "DNA_FILE")]
-        input: String,
+        /// Input DNA FASTA file(s) (the "Soup"; one per tube for multi-tube archives)
+        #[arg(value_name = "DNA_FILE", required = true, num_args = 1..)]
+        input: Vec<String>,
 
-        /// Output binary path for the restored file
+        /// Output binary path for the restored file.
+        /// If a directory, the original file name from the archive manifest is used.
         #[arg(value_name = "OUTPUT_FILE")]
         output: String,
 
+        /// Tube map from a multi-tube compile (supplies per-tube primers and RS geometry)
+        #[arg(long, value_name = "MAP_FILE")]
+        map: Option<String>,
+
+        /// Trellis seed policy override. Normally read from the archive's provenance strand.
+        #[arg(long, value_name = "POLICY")]
+        seed: Option<SeedPolicy>,
+
         /// Molecular identifier tag to target in the soup
         #[arg(long, default_value = "default", value_name = "TAG_ID")]
         tag: String,
@@ -94,13 +133,62 @@ pub enum Commands {
         #[arg(long, value_name = "PASSWORD")]
         password: Option<String>,
 
-        /// Number of data shards (N) used during compilation
-        #[arg(long, default_value_t = 10, value_name = "N")]
-        data: usize,
+        /// Number of data shards (N) used during compilation.
+        /// Normally read from the archive manifest. Without one, defaults to 10 (RS) or
+        /// 100 (fountain).
+        #[arg(long, value_name = "N")]
+        data: Option<usize>,
 
-        /// Number of parity shards (K) used during compilation
-        #[arg(long, default_value_t = 5, value_name = "K")]
-        parity: usize,
+        /// Number of parity shards (K) used during compilation.
+        /// Normally read from the archive manifest. Without one, defaults to 5 (RS) or
+        /// the 1.5x fountain surplus (50 at N=100).
+        #[arg(long, value_name = "K")]
+        parity: Option<usize>,
+
+        /// Block erasure code override (`rs` or `fountain`). Normally read from the manifest.
+        #[arg(long, value_name = "CODEC")]
+        codec: Option<Codec>,
+
+        /// Reads a strand needs before its shard is trusted. Reads that fail alone are
+        /// voted into a consensus once a strand has at least max(2, N) of them.
+        #[arg(long, default_value_t = 1, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=1000))]
+        min_coverage: u32,
+    },
+
+    /// Report archive provenance and strand statistics without restoring.
+    #[command(visible_alias = "info")]
+    Inspect {
+        /// Input DNA FASTA file
+        #[arg(value_name = "DNA_FILE")]
+        input: String,
+
+        /// Molecular identifier tag used during compilation
+        #[arg(long, default_value = "default", value_name = "TAG_ID")]
+        tag: String,
+
+        /// Custom Forward Primer (overrides tag derivation)
+        #[arg(long, value_name = "SEQ")]
+        primer_fwd: Option<String>,
+
+        /// Custom Reverse Primer (overrides tag derivation)
+        #[arg(long, value_name = "SEQ")]
+        primer_rev: Option<String>,
+
+        /// Tube map from a multi-tube compile (supplies per-tube primers)
+        #[arg(long, value_name = "MAP_FILE")]
+        map: Option<String>,
+    },
+
+    /// Verify a restored file against the original, byte for byte.
+    #[command(visible_alias = "cmp")]
+    Compare {
+        /// The original input file
+        #[arg(value_name = "ORIGINAL")]
+        original: String,
+
+        /// The restored output file
+        #[arg(value_name = "RESTORED")]
+        restored: String,
     },
 
     /// Simulate physical DNA decay (Strand Dropout and Mutations).
@@ -122,6 +210,15 @@ pub enum Commands {
         /// e.g. 0.01 is a 1% error rate per base.
         #[arg(short = 'm', long, default_value_t = 0.0, value_name = "RATE")]
         mutation: f32,
+
+        /// JSON error profile (substitution matrix, indels, dropout, coverage).
+        /// Replaces --dropout and --mutation.
+        #[arg(long, value_name = "PROFILE_JSON", conflicts_with_all = ["dropout", "mutation"])]
+        profile: Option<String>,
+
+        /// Seeds the profile simulator so a run can be reproduced exactly.
+        #[arg(long, value_name = "SEED", requires = "profile")]
+        rng_seed: Option<u64>,
     },
 
     /// Filter the 'Soup' for specific molecular tags (In-Silico PCR).
@@ -148,3 +245,13 @@ pub enum Commands {
         output: String,
     }
 }
+
+/// Parses a fountain overhead such as `1.5x` (or plain `1.5`).
+fn parse_overhead(s: &str) -> Result<f64, String> {
+    let ratio: f64 = s.trim().trim_end_matches(['x', 'X']).parse()
+    .map_err(|_| format!("invalid overhead '{}' (expected e.g. 1.5x)", s))?;
+    if !(1.0..=MAX_OVERHEAD).contains(&ratio) {
+        return Err(format!("overhead must be between 1.0x and {}x", MAX_OVERHEAD));
+    }
+    Ok(ratio)
+}
@@ -0,0 +1,517 @@
+// src/consensus.rs
+// READ CONSENSUS
+// Sequencing returns many noisy reads of every synthesized strand. A read that passes
+// CRC32 on its own is used as-is; reads that fail are pooled per shard and voted into
+// a consensus strand, which then runs through the normal decode pipeline.
+//
+// Clustering: reads are grouped by block (FASTA header), then by shard index decoded
+// from the read's address. A read whose address does not decode joins the most similar
+// cluster of its block instead (or starts an anonymous one, named by its consensus).
+//
+// Voting: reads of the cluster's modal length are stacked position by position and the
+// counts drive `DnaMapper::viterbi_vote`, so the consensus always obeys the trellis.
+// FASTA carries no base qualities: every read casts one vote per position. Off-length
+// reads (indels) do not vote; they can still decode alone through indel recovery.
+//
+// Consensus is attempted once a cluster holds `max(2, min_coverage)` failed reads, then
+// again each time that count doubles, and a last time when the stream ends.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(not(feature = "parallel"))]
+use crate::sequential::*;
+use std::collections::{HashMap, HashSet};
+use crate::dna_mapper::{Base, DnaMapper};
+use crate::fasta_header::StrandHeader;
+use crate::oligo::{Oligo, SeedPolicy, ADDRESS_BASE_LEN, PRIMER_TOLERANCE};
+use crate::parallel::ParallelProcessor;
+use crate::telemetry::DecodeOutcome;
+
+/// Share of differing positions above which two reads belong to different strands.
+/// Unrelated trellis sequences differ at about 3/4 of positions.
+const MAX_DIVERGENCE: f64 = 0.25;
+
+/// A shard recovered for the block assembler: `(block_id, index, data)`.
+pub type Shard = (u32, usize, Vec<u8>);
+
+/// Votes a stack of cores (primers stripped) into one consensus core.
+/// Only reads of the modal length vote; `None` if fewer than two do.
+pub fn call_consensus(cores: &[String], fp: &str, seeds: SeedPolicy) -> Option<String> {
+    let mut lengths: HashMap<usize, usize> = HashMap::new();
+    for core in cores {
+        *lengths.entry(core.len()).or_default() += 1;
+    }
+    let (modal_len, voters) = lengths.into_iter().max_by_key(|&(len, count)| (count, len))?;
+    if voters < 2 || modal_len <= ADDRESS_BASE_LEN { return None; }
+
+    let mut votes = vec![[0u32; 4]; modal_len];
+    for core in cores.iter().filter(|c| c.len() == modal_len) {
+        for (ballot, c) in votes.iter_mut().zip(core.chars()) {
+            if let Some(b) = Base::from_char(c) {
+                ballot[b.idx()] += 1;
+            }
+        }
+    }
+
+    // Address and Payload are separate trellis segments (Fixed seeds restart at the seam).
+    let address = DnaMapper::viterbi_vote(&votes[..ADDRESS_BASE_LEN], seeds.address_seed(fp)?)?;
+    let payload = DnaMapper::viterbi_vote(&votes[ADDRESS_BASE_LEN..], seeds.payload_seed(&address)?)?;
+    Some(address + &payload)
+}
+
+/// Reads attributed to one strand of one block.
+#[derive(Debug)]
+struct Cluster {
+    /// Shard index, once known (decoded address or a verified read).
+    index: Option<usize>,
+    /// Primer pair (tube) the first read matched.
+    primers: usize,
+    /// Header of the first read; reused for the consensus strand.
+    header: String,
+    /// First core seen, for similarity matching of reads with a damaged address.
+    representative: String,
+    /// Failed reads awaiting a vote.
+    reads: Vec<String>,
+    /// Every read attributed to this strand, verified or not.
+    coverage: u32,
+    /// Verified shard; handed out once `coverage` reaches the minimum.
+    shard: Option<Vec<u8>>,
+    released: bool,
+    /// Failed-read count that triggers the next consensus attempt.
+    next_attempt: usize,
+    /// Failed-read count at the last attempt.
+    attempted: usize,
+}
+
+/// End-of-restore coverage summary.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoverageStats {
+    /// Distinct data strands (block, index) that received at least one read.
+    pub strands: usize,
+    pub min: u32,
+    pub median: u32,
+    pub mean: f64,
+    pub max: u32,
+    /// Strands seen exactly once.
+    pub single_read: usize,
+    pub consensus_calls: u64,
+    /// Shards recovered only through consensus.
+    pub consensus_rescued: u64,
+    /// Strands with a verified shard that never reached `min_coverage`.
+    pub below_min_coverage: usize,
+    /// Failed reads that could not be attributed to any shard.
+    pub unassigned_reads: u64,
+}
+
+/// Collects reads during restore and releases shards once they are trustworthy.
+pub struct ConsensusPool {
+    primer_sets: Vec<(String, String)>,
+    seeds: SeedPolicy,
+    total_shards: usize,
+    min_coverage: usize,
+    blocks: HashMap<u32, Vec<Cluster>>,
+    /// Blocks already reconstructed: reads only count towards coverage.
+    retired: HashSet<u32>,
+    consensus_calls: u64,
+    consensus_rescued: u64,
+    late_unassigned: u64,
+}
+
+impl ConsensusPool {
+    pub fn new(primer_sets: &[(String, String)], seeds: SeedPolicy, total_shards: usize, min_coverage: usize) -> Self {
+        Self {
+            primer_sets: primer_sets.to_vec(),
+            seeds,
+            total_shards,
+            min_coverage: min_coverage.max(1),
+            blocks: HashMap::new(),
+            retired: HashSet::new(),
+            consensus_calls: 0,
+            consensus_rescued: 0,
+            late_unassigned: 0,
+        }
+    }
+
+    /// A read that verified on its own. Returns its shard once the strand has
+    /// `min_coverage` reads (immediately with the default of 1).
+    pub fn add_verified(&mut self, header: &str, dna: &str, block_id: u32, index: usize, data: Vec<u8>) -> Option<Shard> {
+        let retired = self.retired.contains(&block_id);
+        let core = if retired { None } else { self.strip(dna) };
+        let clusters = self.blocks.entry(block_id).or_default();
+
+        let at = match clusters.iter().position(|c| c.index == Some(index)) {
+            Some(at) => at,
+            None => {
+                // An anonymous cluster of damaged reads may turn out to be this strand.
+                let similar = core.as_ref().and_then(|(_, core)| Self::nearest(clusters, core, true));
+                match similar {
+                    Some(at) => at,
+                    None => {
+                        let (primers, core) = core.unwrap_or_default();
+                        clusters.push(Self::cluster(Some(index), primers, header, core, self.min_coverage));
+                        clusters.len() - 1
+                    }
+                }
+            }
+        };
+
+        let cluster = &mut clusters[at];
+        cluster.index = Some(index);
+        cluster.coverage += 1;
+        if cluster.shard.is_none() {
+            cluster.shard = Some(data.clone());
+            cluster.reads = Vec::new();
+        }
+        if cluster.released {
+            // Surplus copies still go to the assembler for late-arrival cross-checks.
+            return Some((block_id, index, data));
+        }
+        Self::release(cluster, self.min_coverage).map(|(index, data)| (block_id, index, data))
+    }
+
+    /// A read that failed on its own (CRC, address or shard index). Pools it and
+    /// returns a shard if this read completed a consensus or the coverage minimum.
+    pub fn add_failed(&mut self, header: &str, dna: &str) -> Option<Shard> {
+        let block_id = StrandHeader::parse(header)?.block_id;
+        let (primers, core) = self.strip(dna)?;
+        let index = self.read_index(&core, primers).filter(|&i| i < self.total_shards);
+
+        if self.retired.contains(&block_id) {
+            let cluster = index.and_then(|i| self.blocks.get_mut(&block_id)?.iter_mut().find(|c| c.index == Some(i)));
+            match cluster {
+                Some(cluster) => cluster.coverage += 1,
+                None => self.late_unassigned += 1,
+            }
+            return None;
+        }
+
+        let min_coverage = self.min_coverage;
+        let clusters = self.blocks.entry(block_id).or_default();
+        let by_index = index.and_then(|i| clusters.iter().position(|c| c.index == Some(i)));
+        let at = match by_index.or_else(|| Self::nearest(clusters, &core, false)) {
+            Some(at) => at,
+            None => {
+                let index = index.filter(|i| clusters.iter().all(|c| c.index != Some(*i)));
+                clusters.push(Self::cluster(index, primers, header, core.clone(), min_coverage));
+                clusters.len() - 1
+            }
+        };
+
+        let cluster = &mut clusters[at];
+        cluster.coverage += 1;
+        if cluster.shard.is_some() {
+            // Already verified; this read only adds coverage.
+            return Self::release(cluster, min_coverage).map(|(index, data)| (block_id, index, data));
+        }
+        cluster.reads.push(core);
+        if cluster.reads.len() < cluster.next_attempt {
+            return None;
+        }
+        cluster.next_attempt = cluster.reads.len() * 2;
+
+        let outcome = self.vote(block_id, at);
+        self.resolve(block_id, at, outcome)
+    }
+
+    /// End of stream: a last consensus attempt for every unresolved strand that gained
+    /// reads since its previous attempt. Attempts run in parallel.
+    pub fn flush(&mut self) -> Vec<Shard> {
+        let floor = self.min_coverage.max(2);
+        let due: Vec<(u32, usize)> = self.blocks.iter()
+        .filter(|(block_id, _)| !self.retired.contains(block_id))
+        .flat_map(|(&block_id, clusters)| {
+            clusters.iter().enumerate()
+            .filter(|(_, c)| c.shard.is_none() && c.reads.len() >= floor && c.reads.len() > c.attempted)
+            .map(move |(at, _)| (block_id, at))
+        })
+        .collect();
+
+        let this = &*self;
+        let outcomes: Vec<((u32, usize), DecodeOutcome)> = due.into_par_iter()
+        .map(|(block_id, at)| ((block_id, at), this.decode_cluster(&this.blocks[&block_id][at])))
+        .collect();
+
+        let mut shards = Vec::new();
+        for ((block_id, at), outcome) in outcomes {
+            self.consensus_calls += 1;
+            if let Some(cluster) = self.blocks.get_mut(&block_id).and_then(|c| c.get_mut(at)) {
+                cluster.attempted = cluster.reads.len();
+            }
+            shards.extend(self.resolve(block_id, at, outcome));
+        }
+        shards
+    }
+
+    /// Marks a block as reconstructed: its pooled reads are dropped (coverage is kept).
+    pub fn retire_block(&mut self, block_id: u32) {
+        self.retired.insert(block_id);
+        for cluster in self.blocks.get_mut(&block_id).into_iter().flatten() {
+            cluster.reads = Vec::new();
+            cluster.representative = String::new();
+        }
+    }
+
+    pub fn stats(&self) -> CoverageStats {
+        let mut coverage: Vec<u32> = Vec::new();
+        let mut stats = CoverageStats {
+            consensus_calls: self.consensus_calls,
+            consensus_rescued: self.consensus_rescued,
+            unassigned_reads: self.late_unassigned,
+            ..CoverageStats::default()
+        };
+        for cluster in self.blocks.values().flatten() {
+            match cluster.index {
+                Some(_) => coverage.push(cluster.coverage),
+                None => stats.unassigned_reads += cluster.coverage as u64,
+            }
+            if cluster.shard.is_some() && !cluster.released {
+                stats.below_min_coverage += 1;
+            }
+        }
+        if coverage.is_empty() {
+            return stats;
+        }
+
+        coverage.sort_unstable();
+        stats.strands = coverage.len();
+        stats.min = coverage[0];
+        stats.max = coverage[coverage.len() - 1];
+        stats.median = coverage[coverage.len() / 2];
+        stats.mean = coverage.iter().map(|&c| c as f64).sum::<f64>() / coverage.len() as f64;
+        stats.single_read = coverage.iter().filter(|&&c| c == 1).count();
+        stats
+    }
+
+    fn cluster(index: Option<usize>, primers: usize, header: &str, representative: String, min_coverage: usize) -> Cluster {
+        Cluster {
+            index,
+            primers,
+            header: header.to_string(),
+            representative,
+            reads: Vec::new(),
+            coverage: 0,
+            shard: None,
+            released: false,
+            next_attempt: min_coverage.max(2),
+            attempted: 0,
+        }
+    }
+
+    /// Hands out the verified shard the first time coverage reaches the minimum.
+    fn release(cluster: &mut Cluster, min_coverage: usize) -> Option<(usize, Vec<u8>)> {
+        if cluster.released || (cluster.coverage as usize) < min_coverage {
+            return None;
+        }
+        let data = cluster.shard.clone()?;
+        cluster.released = true;
+        Some((cluster.index?, data))
+    }
+
+    fn vote(&mut self, block_id: u32, at: usize) -> DecodeOutcome {
+        self.consensus_calls += 1;
+        let cluster = &mut self.blocks.get_mut(&block_id).expect("cluster exists")[at];
+        cluster.attempted = cluster.reads.len();
+        self.decode_cluster(&self.blocks[&block_id][at])
+    }
+
+    fn decode_cluster(&self, cluster: &Cluster) -> DecodeOutcome {
+        let (fp, rp) = &self.primer_sets[cluster.primers];
+        ParallelProcessor::decode_consensus(&cluster.header, &cluster.reads, (fp, rp), self.seeds)
+        .with_shard_limit(self.total_shards)
+    }
+
+    /// Applies a consensus outcome. An anonymous cluster that decodes folds into the
+    /// cluster already holding its index; the emptied one stays in place so positions
+    /// held by `flush` remain valid.
+    fn resolve(&mut self, block_id: u32, at: usize, outcome: DecodeOutcome) -> Option<Shard> {
+        let DecodeOutcome::Decoded { index, data, .. } = outcome else { return None };
+        let clusters = self.blocks.get_mut(&block_id)?;
+
+        let target = match clusters.iter().position(|c| c.index == Some(index)) {
+            Some(other) if other != at => {
+                let absorbed = &mut clusters[at];
+                let coverage = std::mem::take(&mut absorbed.coverage);
+                absorbed.reads = Vec::new();
+                absorbed.representative = String::new();
+                clusters[other].coverage += coverage;
+                other
+            }
+            _ => at,
+        };
+
+        let cluster = &mut clusters[target];
+        cluster.index = Some(index);
+        cluster.reads = Vec::new();
+        if cluster.shard.is_none() {
+            cluster.shard = Some(data);
+            self.consensus_rescued += 1;
+        }
+        Self::release(cluster, self.min_coverage).map(|(index, data)| (block_id, index, data))
+    }
+
+    /// Most similar cluster of a block (within `MAX_DIVERGENCE`).
+    fn nearest(clusters: &[Cluster], core: &str, anonymous_only: bool) -> Option<usize> {
+        clusters.iter().enumerate()
+        .filter(|(_, c)| !c.representative.is_empty() && (c.index.is_none() || !anonymous_only))
+        .filter_map(|(at, c)| Some((at, divergence(&c.representative, core)?)))
+        .min_by_key(|&(_, d)| d)
+        .map(|(at, _)| at)
+    }
+
+    /// Finds the primer pair that owns a read and strips it (Hamming gate first).
+    fn strip(&self, dna: &str) -> Option<(usize, String)> {
+        let gate = |fuzzy: bool| {
+            self.primer_sets.iter().enumerate().find_map(|(i, (fp, rp))| {
+                let primers = (fp.as_str(), rp.as_str());
+                let core = if fuzzy {
+                    Oligo::strip_tagged_fuzzy(dna, primers, PRIMER_TOLERANCE)
+                } else {
+                    Oligo::strip_tagged_indel(dna, primers, PRIMER_TOLERANCE)
+                };
+                core.map(|core| (i, core.to_string()))
+            })
+        };
+        gate(true).or_else(|| gate(false))
+    }
+
+    /// Shard index from an undamaged address (no healing: a damaged one clusters by similarity).
+    fn read_index(&self, core: &str, primers: usize) -> Option<usize> {
+        let seed = self.seeds.address_seed(&self.primer_sets[primers].0)?;
+        let bytes = DnaMapper::decode_shard(core.get(..ADDRESS_BASE_LEN)?, seed)?;
+        Some(u32::from_be_bytes(bytes.get(..4)?.try_into().ok()?) as usize)
+    }
+}
+
+/// Differing positions between two cores (length difference included),
+/// or `None` once they exceed `MAX_DIVERGENCE`.
+fn divergence(a: &str, b: &str) -> Option<usize> {
+    let budget = (a.len().max(b.len()) as f64 * MAX_DIVERGENCE) as usize;
+    let mut diff = a.len().abs_diff(b.len());
+    for (x, y) in a.bytes().zip(b.bytes()) {
+        diff += (x != y) as usize;
+        if diff > budget { return None; }
+    }
+    (diff <= budget).then_some(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FP: &str = "ACGTACGTACGTACGTACGT";
+    const RP: &str = "TGCATGCATGCATGCATGCA";
+
+    fn primer_sets() -> Vec<(String, String)> {
+        vec![(FP.to_string(), RP.to_string())]
+    }
+
+    /// `(header, strand, shard)` for shard `index` of block 0.
+    fn strand(index: usize) -> (String, String, Vec<u8>) {
+        let shards: Vec<Vec<u8>> = (0..=index).map(|i| (0..48u8).map(|b| b.wrapping_mul(7).wrapping_add(i as u8 * 31)).collect()).collect();
+        let result = ParallelProcessor::process_block(&StrandHeader::new(0, 0), shards.clone(), (FP, RP), SeedPolicy::Chained);
+        let mut lines = result.shards[index].fasta_entry.lines();
+        (lines.next().unwrap().to_string(), lines.next().unwrap().to_string(), shards[index].clone())
+    }
+
+    /// Substitutes every `step`-th payload base starting at `offset`.
+    fn damage(dna: &str, offset: usize, step: usize) -> String {
+        let start = FP.len() + ADDRESS_BASE_LEN + offset;
+        let end = dna.len() - RP.len();
+        dna.char_indices().map(|(i, c)| {
+            if i >= start && i < end && (i - start).is_multiple_of(step) {
+                match c { 'A' => 'C', 'C' => 'G', 'G' => 'T', _ => 'A' }
+            } else {
+                c
+            }
+        }).collect()
+    }
+
+    fn core(dna: &str) -> String {
+        dna[FP.len()..dna.len() - RP.len()].to_string()
+    }
+
+    #[test]
+    fn only_reads_of_the_modal_length_vote() {
+        let (_, dna, _) = strand(0);
+        let clean = core(&dna);
+        let mut cores: Vec<String> = (0..3).map(|i| core(&damage(&dna, 5 + i * 3, 97))).collect();
+        // Reads carrying an insertion are off the modal length and do not vote.
+        for at in [30, 60] {
+            let mut long = clean.clone();
+            long.insert(at, 'A');
+            cores.push(long);
+        }
+        assert_eq!(call_consensus(&cores, FP, SeedPolicy::Chained), Some(clean.clone()));
+
+        // A single read at the modal length is not a vote.
+        assert_eq!(call_consensus(&cores[..1], FP, SeedPolicy::Chained), None);
+    }
+
+    #[test]
+    fn divergence_rejects_unrelated_strands() {
+        let (_, a, _) = strand(0);
+        let (_, b, _) = strand(1);
+        let (a, b) = (core(&a), core(&b));
+        assert_eq!(divergence(&a, &a), Some(0));
+        assert!(divergence(&a, &core(&damage(&format!("{}{}{}", FP, a, RP), 0, 10))).is_some());
+        assert_eq!(divergence(&a, &b), None);
+    }
+
+    #[test]
+    fn shards_wait_for_min_coverage() {
+        let (header, dna, data) = strand(0);
+        let mut pool = ConsensusPool::new(&primer_sets(), SeedPolicy::Chained, 15, 3);
+
+        assert_eq!(pool.add_verified(&header, &dna, 0, 0, data.clone()), None);
+        assert_eq!(pool.add_verified(&header, &dna, 0, 0, data.clone()), None);
+        assert_eq!(pool.stats().below_min_coverage, 1);
+
+        assert_eq!(pool.add_verified(&header, &dna, 0, 0, data.clone()), Some((0, 0, data.clone())));
+        let stats = pool.stats();
+        assert_eq!((stats.strands, stats.min, stats.below_min_coverage), (1, 3, 0));
+
+        // Surplus copies still reach the assembler for late-arrival cross-checks.
+        assert_eq!(pool.add_verified(&header, &dna, 0, 0, data.clone()), Some((0, 0, data)));
+    }
+
+    #[test]
+    fn flush_votes_pooled_failures() {
+        let (header, dna, data) = strand(0);
+        let mut pool = ConsensusPool::new(&primer_sets(), SeedPolicy::Chained, 15, 1);
+        let wrong = damage(&dna, 0, 8);
+
+        // Attempts at 2 and 4 reads are out-voted by the shared damage; the other
+        // reads are damaged at disjoint positions.
+        let reads = [wrong.clone(), wrong.clone(), wrong, damage(&dna, 1, 8), damage(&dna, 2, 8), damage(&dna, 3, 8), damage(&dna, 4, 8)];
+        for read in &reads {
+            assert_eq!(pool.add_failed(&header, read), None);
+        }
+        assert_eq!(pool.stats().consensus_calls, 2);
+
+        assert_eq!(pool.flush(), vec![(0, 0, data)]);
+        let stats = pool.stats();
+        assert_eq!((stats.consensus_calls, stats.consensus_rescued, stats.min), (3, 1, 7));
+
+        // Nothing new to vote on.
+        assert!(pool.flush().is_empty());
+    }
+
+    #[test]
+    fn retired_blocks_drop_pooled_reads() {
+        let (header, dna, _) = strand(0);
+        let mut pool = ConsensusPool::new(&primer_sets(), SeedPolicy::Chained, 15, 1);
+        pool.add_failed(&header, &damage(&dna, 0, 8));
+        assert_eq!(pool.blocks[&0][0].reads.len(), 1);
+
+        pool.retire_block(0);
+        let cluster = &pool.blocks[&0][0];
+        assert!(cluster.reads.is_empty() && cluster.reads.capacity() == 0);
+        assert!(cluster.representative.is_empty());
+
+        // Late reads only count towards coverage.
+        assert_eq!(pool.add_failed(&header, &damage(&dna, 1, 8)), None);
+        assert_eq!(pool.blocks[&0][0].reads.len(), 0);
+        assert_eq!(pool.stats().min, 2);
+        assert!(pool.flush().is_empty());
+    }
+}
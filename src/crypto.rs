@@ -4,7 +4,7 @@ use argon2::{
 };
 use hkdf::Hkdf;
 use sha2::Sha256;
-use aes_gcm::{Key, Aes256Gcm};
+use aes_gcm::{Key, Aes256Gcm, KeyInit, Nonce, aead::Aead};
 
 /// SLOW: Derives a Master Key from the user password (runs once at startup).
 ///
@@ -31,3 +31,16 @@ pub fn derive_session_key(master_key: &[u8], block_salt: &[u8]) -> Key<Aes256Gcm
     hk.expand(&[], &mut okm).expect("HKDF expansion failed");
     *Key::<Aes256Gcm>::from_slice(&okm)
 }
+
+/// Encrypts one block payload under its HKDF session key (AES-256-GCM).
+pub fn encrypt_block(master_key: &[u8], block_salt: &[u8], nonce: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(&derive_session_key(master_key, block_salt));
+    cipher.encrypt(Nonce::from_slice(nonce), payload)
+    .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))
+}
+
+/// Decrypts one block payload. `None` means a wrong key or a tampered block.
+pub fn decrypt_block(master_key: &[u8], block_salt: &[u8], nonce: &[u8], payload: &[u8]) -> Option<Vec<u8>> {
+    let cipher = Aes256Gcm::new(&derive_session_key(master_key, block_salt));
+    cipher.decrypt(Nonce::from_slice(nonce), payload).ok()
+}
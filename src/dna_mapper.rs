@@ -3,6 +3,9 @@
 // This module handles the translation between Binary Data and Biological Bases (ACGT).
 // It enforces the "No Homopolymer" constraint (e.g., no 'AA', 'GG') mathematically.
 
+use std::collections::{BinaryHeap, HashSet};
+use std::fmt;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Base {
     A, C, G, T,
@@ -40,6 +43,38 @@ pub struct StabilityReport {
     pub is_stable: bool,
 }
 
+/// Strands that stayed unstable through every re-roll of their salt (compile halts
+/// unless forced). `scope` names what was being encoded, e.g. "Block 3".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnstableStrands {
+    pub scope: String,
+    pub unstable: usize,
+    pub retries: usize,
+}
+
+impl fmt::Display for UnstableStrands {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} unstable strands after {} retries.", self.scope, self.unstable, self.retries)
+    }
+}
+
+impl std::error::Error for UnstableStrands {}
+
+/// Largest net length change (inserted minus deleted bases) the indel decoder absorbs.
+pub const MAX_INDEL_SHIFT: usize = 2;
+
+/// Longest strand core the indel decoders attempt. Indel recovery is for synthesis-scale
+/// strands; the search memory and candidate checks grow with length.
+const MAX_INDEL_CORE_LEN: usize = 64 * 1024;
+
+/// Drift band of the indel Viterbi: the net shift plus one base of slack. Compensating
+/// indel pairs are out of reach: the read usually stays a valid trellis path, so it is
+/// its own closest match.
+const INDEL_BAND: usize = MAX_INDEL_SHIFT + 1;
+
+/// Edits of each sequence kept by the frame resync search (candidates grow as BRANCH^shift).
+const RESYNC_BRANCH: usize = 32;
+
 pub struct DnaMapper;
 
 impl DnaMapper {
@@ -135,8 +170,27 @@ impl DnaMapper {
         let observed: Vec<Base> = noisy_dna.chars().filter_map(Base::from_char).collect();
         if observed.len() != n { return None; } // Garbage characters present
 
+        // A single read is a one-vote ballot per position.
+        let votes: Vec<[u32; 4]> = observed.iter().map(|b| {
+            let mut ballot = [0; 4];
+            ballot[b.idx()] = 1;
+            ballot
+        }).collect();
+        Self::viterbi_vote(&votes, start_base)
+    }
+
+    /// CONSENSUS VITERBI (Voting over the Trellis)
+    ///
+    /// Generalizes `viterbi_correct` to many aligned reads: `votes[i][b]` counts the reads
+    /// showing base `b` at position `i`. Emitting `b` costs the votes against it, so the
+    /// result is the valid (homopolymer-free) sequence that the fewest read bases disagree
+    /// with. A plain per-position majority could pick an illegal transition on a split vote.
+    pub fn viterbi_vote(votes: &[[u32; 4]], start_base: Base) -> Option<String> {
+        let n = votes.len();
+        if n == 0 { return None; }
+
         // DP State Matrix: dp[step][current_base] = (min_cost, parent_base)
-        // We use a simplified cost model: 0 for match, 1 for mismatch (Hamming).
+        // Cost model: the number of votes for other bases (Hamming for a single read).
         let mut dp = vec![vec![(u32::MAX, Base::A); 4]; n + 1];
 
         // Initialization: Step 0 is constrained to start_base (cost 0)
@@ -151,7 +205,8 @@ impl DnaMapper {
 
         // Forward Pass: Fill the DP Matrix
         for i in 1..=n {
-            let obs_base = observed[i-1];
+            let ballot = &votes[i-1];
+            let turnout: u32 = ballot.iter().sum();
 
             for curr in Base::all() {
                 let mut best_cost = u32::MAX;
@@ -166,8 +221,8 @@ impl DnaMapper {
                     if dp[i-1][prev.idx()].0 == u32::MAX { continue; }
 
                     // Cost Calculation:
-                    // Accumulated Cost (from prev) + Emission Cost (Hamming: Is curr == obs?)
-                    let emission_cost = if curr == obs_base { 0 } else { 1 };
+                    // Accumulated Cost (from prev) + Emission Cost (votes against curr)
+                    let emission_cost = turnout - ballot[curr.idx()];
                     let total_cost = dp[i-1][prev.idx()].0.saturating_add(emission_cost);
 
                     if total_cost < best_cost {
@@ -206,6 +261,236 @@ impl DnaMapper {
         Some(corrected_path.iter().map(|b| b.to_char()).collect())
     }
 
+    /// INDEL-TOLERANT VITERBI (Levenshtein over the Trellis)
+    ///
+    /// Substitutions keep the frame; insertions and deletions shift every following trit,
+    /// which the Hamming decoder above cannot undo. This variant searches for the valid
+    /// path of exactly `target_len` bases with the minimum edit distance to the observed
+    /// string (substitution, insertion and deletion each cost 1).
+    ///
+    /// `reseed` restarts the trellis at an output position from a fixed base, for
+    /// segments that are not chained to their predecessor.
+    ///
+    /// The search is banded to `INDEL_BAND` bases of drift, so memory stays
+    /// O(N * band) and strands far off the expected length are rejected outright.
+    pub fn viterbi_correct_indels(noisy_dna: &str, start_base: Base, target_len: usize, reseed: Option<(usize, Base)>) -> Option<String> {
+        const W: usize = INDEL_BAND;
+        const DIAGS: usize = 2 * W + 1;
+        const UNREACHED: u8 = u8::MAX;
+        const MATCH: u8 = 0;
+        const DELETION: u8 = 1;  // Base missing from the read: emit without consuming
+        const INSERTION: u8 = 2; // Extra base in the read: consume without emitting
+
+        let observed: Vec<Base> = noisy_dna.chars().map(Base::from_char).collect::<Option<_>>()?;
+        let n = observed.len();
+        if target_len == 0 || target_len > MAX_INDEL_CORE_LEN || n.abs_diff(target_len) > W { return None; }
+
+        // Cell (j, d, b): j output bases emitted, i = j + d - W observed bases consumed,
+        // last emitted base b. Costs roll per row; backpointers are kept for traceback.
+        let cell = |d: usize, b: usize| d * 4 + b;
+        let obs_index = |j: usize, d: usize| (j + d).checked_sub(W).filter(|&i| i <= n);
+        let allowed = |j: usize, prev: usize, curr: usize| match reseed {
+            Some((at, seed)) if j == at + 1 => curr != seed.idx(),
+            _ => curr != prev,
+        };
+
+        let mut back = vec![UNREACHED; (target_len + 1) * DIAGS * 4];
+        let mut row = [u32::MAX; DIAGS * 4];
+
+        // Row 0: only the seed state; leading read bases can be insertions.
+        for d in W..DIAGS {
+            if let Some(i) = obs_index(0, d) {
+                row[cell(d, start_base.idx())] = i as u32;
+                back[cell(d, start_base.idx())] = (INSERTION << 2) | start_base.idx() as u8;
+            }
+        }
+
+        for j in 1..=target_len {
+            let prev_row = row;
+            row = [u32::MAX; DIAGS * 4];
+            let row_back = j * DIAGS * 4;
+
+            // Ascending d, so same-row insertions (d - 1) are final before use.
+            for d in 0..DIAGS {
+                let Some(i) = obs_index(j, d) else { continue };
+                for curr in 0..4 {
+                    let mut best = (u32::MAX, UNREACHED);
+
+                    for prev in 0..4 {
+                        if !allowed(j, prev, curr) { continue; }
+                        // Match / Substitution: (j-1, i-1) -> same diagonal
+                        if i > 0 {
+                            let c = prev_row[cell(d, prev)];
+                            if c != u32::MAX {
+                                let cost = c + (observed[i - 1].idx() != curr) as u32;
+                                if cost < best.0 { best = (cost, (MATCH << 2) | prev as u8); }
+                            }
+                        }
+                        // Deletion: (j-1, i) -> diagonal d + 1 in the previous row
+                        if d + 1 < DIAGS {
+                            let c = prev_row[cell(d + 1, prev)];
+                            if c != u32::MAX && c + 1 < best.0 {
+                                best = (c + 1, (DELETION << 2) | prev as u8);
+                            }
+                        }
+                    }
+                    // Insertion: (j, i-1) -> diagonal d - 1 in this row, same base
+                    if d > 0 && i > 0 {
+                        let c = row[cell(d - 1, curr)];
+                        if c != u32::MAX && c + 1 < best.0 {
+                            best = (c + 1, (INSERTION << 2) | curr as u8);
+                        }
+                    }
+
+                    row[cell(d, curr)] = best.0;
+                    back[row_back + cell(d, curr)] = best.1;
+                }
+            }
+        }
+
+        // The path must consume the whole read: i = n at j = target_len.
+        let d_end = n + W - target_len;
+        let (mut curr, _) = (0..4)
+        .map(|b| (b, row[cell(d_end, b)]))
+        .filter(|&(_, c)| c != u32::MAX)
+        .min_by_key(|&(_, c)| c)?;
+
+        // Traceback
+        let mut path = Vec::with_capacity(target_len);
+        let (mut j, mut d) = (target_len, d_end);
+        while j > 0 {
+            let ptr = back[j * DIAGS * 4 + cell(d, curr)];
+            if ptr == UNREACHED { return None; }
+            let (mv, prev) = (ptr >> 2, (ptr & 0b11) as usize);
+            match mv {
+                MATCH => { path.push(curr); j -= 1; }
+                DELETION => { path.push(curr); j -= 1; d += 1; }
+                _ => { d -= 1; } // Insertion: stay on this output base
+            }
+            curr = prev;
+        }
+
+        path.reverse();
+        let bases = Base::all();
+        Some(path.into_iter().map(|b| bases[b].to_char()).collect())
+    }
+
+    /// FRAME RESYNC (Indel Localization)
+    ///
+    /// The edit-distance Viterbi finds *a* cheapest alignment, but a lost base can usually
+    /// be restored at many positions for the same cost; only the CRC can tell them apart.
+    /// The byte packing narrows the search: 6 trits carry one byte, so only 256 of the 729
+    /// chunk values are legal. After an indel the chunk grid slips and about two thirds of
+    /// the following chunks decode out of range, which pins the edit to a chunk or two.
+    ///
+    /// For each missing (extra) base, every single-base insertion (removal) is scored by
+    /// the number of illegal chunks it leaves, in O(N) per sequence, and the best
+    /// `RESYNC_BRANCH` edits of every sequence carry on to the next one.
+    ///
+    /// Returns candidates of exactly `target_len` bases, best first; the caller verifies them.
+    pub fn resync_candidates(noisy_dna: &str, start_base: Base, target_len: usize) -> Vec<String> {
+        let Some(observed) = noisy_dna.chars().map(Base::from_char).collect::<Option<Vec<_>>>() else {
+            return Vec::new();
+        };
+        let shift = observed.len().abs_diff(target_len);
+        if target_len == 0 || target_len > MAX_INDEL_CORE_LEN || shift == 0 || shift > MAX_INDEL_SHIFT {
+            return Vec::new();
+        }
+        let grow = observed.len() < target_len;
+
+        let mut beam = vec![observed];
+        for _ in 0..shift {
+            let mut next: Vec<Vec<Base>> = Vec::new();
+            let mut seen: HashSet<Vec<Base>> = HashSet::new();
+            for seq in &beam {
+                // Max-heap capped at RESYNC_BRANCH keeps the lowest (score, position, base).
+                let mut best: BinaryHeap<(usize, usize, usize)> = BinaryHeap::new();
+                Self::score_edits(seq, start_base, grow, |score, q, c| {
+                    best.push((score, q, c));
+                    if best.len() > RESYNC_BRANCH { best.pop(); }
+                });
+                for (_, q, c) in best.into_sorted_vec() {
+                    let mut edited = seq.clone();
+                    if grow { edited.insert(q, Base::all()[c]); } else { edited.remove(q); }
+                    if seen.insert(edited.clone()) { next.push(edited); }
+                }
+            }
+            beam = next;
+        }
+
+        beam.into_iter().map(|seq| seq.into_iter().map(Base::to_char).collect()).collect()
+    }
+
+    /// Scores every single-base edit of `seq` towards the target length:
+    /// `visit(illegal_chunks, position, base index)`. Removals report base index 0.
+    fn score_edits(seq: &[Base], start_base: Base, grow: bool, mut visit: impl FnMut(usize, usize, usize)) {
+        let n = seq.len();
+        let prev_of = |k: usize| if k == 0 { start_base } else { seq[k - 1] };
+        let trits: Vec<Option<u8>> = (0..n).map(|k| Self::prev_trit(prev_of(k), seq[k])).collect();
+        let new_len = if grow { n + 1 } else { n - 1 };
+        let chunks = new_len.div_ceil(6);
+
+        // A chunk is legal if it is complete, obeys the trellis and packs a value < 256.
+        let illegal = |trit_at: &dyn Fn(usize) -> Option<u8>, m: usize| -> bool {
+            let mut val: u32 = 0;
+            let mut power: u32 = 1;
+            for p in 6 * m..6 * m + 6 {
+                if p >= new_len { return true; }
+                match trit_at(p) {
+                    Some(t) => { val += t as u32 * power; power *= 3; }
+                    None => return true,
+                }
+            }
+            val > 255
+        };
+
+        // Chunks before the edit keep the original grid; chunks after it see every
+        // trit shifted by one position. Both are the same for all edit positions.
+        let mut head = vec![0usize; chunks + 1];
+        for m in 0..chunks {
+            head[m + 1] = head[m] + illegal(&|p| if p < n { trits[p] } else { None }, m) as usize;
+        }
+        let shifted = |p: usize| -> Option<u8> {
+            let k = if grow { p.checked_sub(1)? } else { p + 1 };
+            *trits.get(k)?
+        };
+        let mut tail = vec![0usize; chunks + 1];
+        for m in (0..chunks).rev() {
+            tail[m] = tail[m + 1] + illegal(&shifted, m) as usize;
+        }
+
+        if grow {
+            for q in 0..=n {
+                for c in Base::all() {
+                    if c == prev_of(q) || seq.get(q) == Some(&c) { continue; }
+                    let trit_at = |p: usize| -> Option<u8> {
+                        match p {
+                            p if p < q => trits[p],
+                            p if p == q => Self::prev_trit(prev_of(q), c),
+                            p if p == q + 1 => Self::prev_trit(c, seq[q]),
+                            p => trits[p - 1],
+                        }
+                    };
+                    let (m0, m1) = (q / 6, ((q + 1) / 6).min(chunks - 1));
+                    let local: usize = (m0..=m1).map(|m| illegal(&trit_at, m) as usize).sum();
+                    visit(head[m0] + local + tail[m1 + 1], q, c.idx());
+                }
+            }
+        } else {
+            for q in 0..n {
+                let trit_at = |p: usize| -> Option<u8> {
+                    match p {
+                        p if p < q => trits[p],
+                        p if p == q => Self::prev_trit(prev_of(q), seq[q + 1]),
+                        p => trits[p + 1],
+                    }
+                };
+                let m0 = (q / 6).min(chunks - 1);
+                visit(head[m0] + illegal(&trit_at, m0) as usize + tail[m0 + 1], q, 0);
+            }
+        }
+    }
+
     /// Analyzes the biological stability of a DNA strand.
     /// Checks GC Content (should be 40-60%) and Melting Temp (Tm > 50C).
     pub fn analyze_stability(dna: &str) -> StabilityReport {
@@ -231,7 +516,85 @@ impl DnaMapper {
         let salt_adjust = 16.6 * na_conc.log10();
         let melting_temp = 81.5 + salt_adjust + (0.41 * gc_content) - (600.0 / len);
 
-        let is_stable = (gc_content >= 40.0 && gc_content <= 60.0) && (melting_temp > 50.0);
+        let is_stable = (40.0..=60.0).contains(&gc_content) && (melting_temp > 50.0);
         StabilityReport { gc_content, melting_temp, is_stable }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (Vec<u8>, String) {
+        let data: Vec<u8> = (0..24u8).map(|i| i.wrapping_mul(37).wrapping_add(11)).collect();
+        let dna = DnaMapper::encode_shard(&data, Base::G);
+        (data, dna)
+    }
+
+    /// Edit distance, to check the indel Viterbi stays within the injected damage.
+    fn levenshtein(a: &str, b: &str) -> usize {
+        let (a, b) = (a.as_bytes(), b.as_bytes());
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for i in 1..=a.len() {
+            let mut diag = row[0];
+            row[0] = i;
+            for j in 1..=b.len() {
+                let next = (row[j] + 1).min(row[j - 1] + 1).min(diag + (a[i - 1] != b[j - 1]) as usize);
+                diag = row[j];
+                row[j] = next;
+            }
+        }
+        row[b.len()]
+    }
+
+    #[test]
+    fn resync_restores_a_single_insertion() {
+        let (data, dna) = sample();
+        let mut noisy = dna.clone();
+        noisy.insert(50, if &dna[50..51] == "A" { 'C' } else { 'A' });
+
+        let candidates = DnaMapper::resync_candidates(&noisy, Base::G, dna.len());
+        assert!(candidates.iter().all(|c| c.len() == dna.len()));
+        assert!(candidates.iter().any(|c| DnaMapper::decode_shard(c, Base::G).as_ref() == Some(&data)));
+    }
+
+    #[test]
+    fn resync_restores_a_single_deletion() {
+        let (data, dna) = sample();
+        let mut noisy = dna.clone();
+        noisy.remove(71);
+
+        let candidates = DnaMapper::resync_candidates(&noisy, Base::G, dna.len());
+        assert!(candidates.iter().all(|c| c.len() == dna.len()));
+        assert!(candidates.iter().any(|c| DnaMapper::decode_shard(c, Base::G).as_ref() == Some(&data)));
+    }
+
+    #[test]
+    fn resync_ignores_reads_on_the_byte_grid() {
+        let (_, dna) = sample();
+        assert!(DnaMapper::resync_candidates(&dna, Base::G, dna.len()).is_empty());
+    }
+
+    #[test]
+    fn indel_viterbi_returns_a_valid_path_one_edit_away() {
+        let (_, dna) = sample();
+        let mut inserted = dna.clone();
+        inserted.insert(30, if &dna[30..31] == "T" { 'G' } else { 'T' });
+        let mut deleted = dna.clone();
+        deleted.remove(90);
+
+        for noisy in [inserted, deleted] {
+            let path = DnaMapper::viterbi_correct_indels(&noisy, Base::G, dna.len(), None).unwrap();
+            assert_eq!(path.len(), dna.len());
+            assert!(DnaMapper::decode_shard(&path, Base::G).is_some());
+            assert_eq!(levenshtein(&path, &noisy), 1);
+        }
+    }
+
+    #[test]
+    fn indel_viterbi_rejects_shifts_beyond_the_band() {
+        let (_, dna) = sample();
+        let truncated = &dna[..dna.len() - (INDEL_BAND + 1)];
+        assert_eq!(DnaMapper::viterbi_correct_indels(truncated, Base::G, dna.len(), None), None);
+    }
+}
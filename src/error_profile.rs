@@ -0,0 +1,273 @@
+// src/error_profile.rs
+// ERROR PROFILES
+// User-defined channel models for the decay simulator.
+// Lets error rates measured from real synthesis/sequencing runs (or taken from
+// published papers) drive `simulate` instead of only the uniform built-in flags.
+//
+// Format (JSON):
+// {
+//   "name": "nanopore-r9.4",
+//   "dropout": 0.15,
+//   "substitution": { "A": { "G": 0.004, "C": 0.001 }, "C": { "T": 0.004 } },
+//   "insertion": 0.002,
+//   "deletion": 0.003,
+//   "coverage": { "distribution": "poisson", "mean": 8.0 }
+// }
+// Every field is optional; omitted rates default to zero, coverage to one copy.
+
+use std::collections::BTreeMap;
+use std::fs;
+use anyhow::{Result, Context, bail};
+use rand::Rng;
+use serde::Deserialize;
+use crate::dna_mapper::Base;
+
+/// How many reads each surviving strand produces.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "distribution", rename_all = "snake_case")]
+pub enum Coverage {
+    /// Exactly `copies` reads per strand.
+    Fixed { copies: u32 },
+    /// Poisson-distributed read count (classic sequencing depth model).
+    /// A draw of 0 acts as an additional, coverage-driven dropout.
+    Poisson { mean: f64 },
+    /// Uniform read count in `[min, max]`.
+    Uniform { min: u32, max: u32 },
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Coverage::Fixed { copies: 1 }
+    }
+}
+
+impl Coverage {
+    /// Draws the number of reads for one strand.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> u32 {
+        match *self {
+            Coverage::Fixed { copies } => copies,
+            Coverage::Uniform { min, max } => rng.gen_range(min..=max),
+            Coverage::Poisson { mean } => {
+                // Knuth's method: fine for the small means typical of read depth.
+                let limit = (-mean).exp();
+                let mut k = 0;
+                let mut p = 1.0;
+                loop {
+                    p *= rng.gen::<f64>();
+                    if p <= limit { return k; }
+                    k += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Raw on-disk representation, validated into an `ErrorProfile`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileFile {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    dropout: f64,
+    #[serde(default)]
+    substitution: BTreeMap<String, BTreeMap<String, f64>>,
+    #[serde(default)]
+    insertion: f64,
+    #[serde(default)]
+    deletion: f64,
+    #[serde(default)]
+    coverage: Coverage,
+}
+
+/// A validated channel model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorProfile {
+    pub name: String,
+    /// Probability (0-1) that a strand is lost entirely.
+    pub dropout: f64,
+    /// `substitution[from][to]`: probability that base `from` is read as `to`.
+    /// Indexed by `Base::idx()`. The diagonal is always zero.
+    pub substitution: [[f64; 4]; 4],
+    /// Per-base probability of a random base being inserted after it.
+    pub insertion: f64,
+    /// Per-base probability of the base being deleted.
+    pub deletion: f64,
+    pub coverage: Coverage,
+}
+
+impl ErrorProfile {
+    /// Loads and validates a profile from a JSON file.
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path).context(format!("Failed to read profile: {}", path))?;
+        Self::from_json(&text).context(format!("Invalid error profile: {}", path))
+    }
+
+    /// Parses and validates a profile from JSON text.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let raw: ProfileFile = serde_json::from_str(text)?;
+
+        let check_rate = |label: &str, p: f64| -> Result<()> {
+            if !(0.0..=1.0).contains(&p) {
+                bail!("{} must be within 0.0-1.0 (got {})", label, p);
+            }
+            Ok(())
+        };
+
+        check_rate("dropout", raw.dropout)?;
+        check_rate("insertion", raw.insertion)?;
+        check_rate("deletion", raw.deletion)?;
+
+        let parse_base = |s: &str| -> Result<Base> {
+            let mut chars = s.chars();
+            match (chars.next().and_then(Base::from_char), chars.next()) {
+                (Some(b), None) => Ok(b),
+                _ => bail!("substitution keys must be one of A/C/G/T (got '{}')", s),
+            }
+        };
+
+        let mut substitution = [[0.0; 4]; 4];
+        for (from, row) in &raw.substitution {
+            let from_base = parse_base(from)?;
+            for (to, &p) in row {
+                let to_base = parse_base(to)?;
+                if to_base == from_base {
+                    bail!("substitution {}->{} is not a substitution", from, to);
+                }
+                check_rate(&format!("substitution {}->{}", from, to), p)?;
+                substitution[from_base.idx()][to_base.idx()] = p;
+            }
+            let row_sum: f64 = substitution[from_base.idx()].iter().sum();
+            if row_sum > 1.0 {
+                bail!("substitution rates from {} sum to {} (> 1.0)", from, row_sum);
+            }
+        }
+
+        match raw.coverage {
+            Coverage::Poisson { mean } if !(mean > 0.0 && mean <= 500.0) => {
+                bail!("poisson coverage mean must be within (0, 500] (got {})", mean);
+            }
+            Coverage::Uniform { min, max } if min > max => {
+                bail!("uniform coverage min ({}) exceeds max ({})", min, max);
+            }
+            _ => {}
+        }
+
+        Ok(Self {
+            name: raw.name.unwrap_or_else(|| "custom".to_string()),
+            dropout: raw.dropout,
+            substitution,
+            insertion: raw.insertion,
+            deletion: raw.deletion,
+            coverage: raw.coverage,
+        })
+    }
+
+    /// Passes one strand through the channel (substitutions and indels).
+    /// Non-ACGT characters are copied through untouched.
+    pub fn corrupt<R: Rng>(&self, dna: &str, rng: &mut R) -> String {
+        let bases = Base::all();
+        let mut out = String::with_capacity(dna.len() + dna.len() / 16);
+
+        for c in dna.chars() {
+            // 1. Deletion
+            if self.deletion > 0.0 && rng.gen::<f64>() < self.deletion { continue; }
+
+            // 2. Substitution (row lookup in the confusion matrix)
+            let read = match Base::from_char(c) {
+                Some(b) => {
+                    let roll = rng.gen::<f64>();
+                    let mut acc = 0.0;
+                    let mut chosen = b;
+                    for to in bases {
+                        acc += self.substitution[b.idx()][to.idx()];
+                        if roll < acc {
+                            chosen = to;
+                            break;
+                        }
+                    }
+                    chosen.to_char()
+                }
+                None => c,
+            };
+            out.push(read);
+
+            // 3. Insertion
+            if self.insertion > 0.0 && rng.gen::<f64>() < self.insertion {
+                out.push(bases[rng.gen_range(0..4)].to_char());
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const NANOPORE: &str = r#"{
+        "name": "nanopore-r9.4",
+        "dropout": 0.15,
+        "substitution": { "A": { "G": 0.004, "C": 0.001 }, "C": { "T": 0.004 } },
+        "insertion": 0.002,
+        "deletion": 0.003,
+        "coverage": { "distribution": "poisson", "mean": 8.0 }
+    }"#;
+
+    #[test]
+    fn parses_the_documented_format() {
+        let p = ErrorProfile::from_json(NANOPORE).unwrap();
+        assert_eq!(p.name, "nanopore-r9.4");
+        assert_eq!((p.dropout, p.insertion, p.deletion), (0.15, 0.002, 0.003));
+        assert_eq!(p.substitution[Base::A.idx()][Base::G.idx()], 0.004);
+        assert_eq!(p.substitution[Base::C.idx()][Base::T.idx()], 0.004);
+        assert_eq!(p.substitution[Base::G.idx()], [0.0; 4]);
+        assert_eq!(p.coverage, Coverage::Poisson { mean: 8.0 });
+    }
+
+    #[test]
+    fn omitted_fields_default_to_a_clean_single_read() {
+        let p = ErrorProfile::from_json("{}").unwrap();
+        assert_eq!(p.name, "custom");
+        assert_eq!((p.dropout, p.insertion, p.deletion), (0.0, 0.0, 0.0));
+        assert_eq!(p.coverage, Coverage::Fixed { copies: 1 });
+    }
+
+    #[test]
+    fn rejects_invalid_profiles() {
+        for bad in [
+            r#"{ "dropout": 1.5 }"#,
+            r#"{ "insertion": -0.1 }"#,
+            r#"{ "deletion": 2 }"#,
+            r#"{ "substitution": { "A": { "C": 1.2 } } }"#,
+            r#"{ "substitution": { "A": { "C": 0.6, "G": 0.6 } } }"#,
+            r#"{ "substitution": { "A": { "A": 0.1 } } }"#,
+            r#"{ "substitution": { "N": { "A": 0.1 } } }"#,
+            r#"{ "coverage": { "distribution": "gamma", "shape": 2.0 } }"#,
+            r#"{ "coverage": { "distribution": "poisson", "mean": 0.0 } }"#,
+            r#"{ "coverage": { "distribution": "uniform", "min": 5, "max": 2 } }"#,
+            r#"{ "mutation": 0.01 }"#,
+        ] {
+            assert!(ErrorProfile::from_json(bad).is_err(), "accepted {}", bad);
+        }
+    }
+
+    #[test]
+    fn coverage_draws_stay_in_range() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(Coverage::Fixed { copies: 3 }.sample(&mut rng), 3);
+        for _ in 0..200 {
+            assert!((2..=4).contains(&Coverage::Uniform { min: 2, max: 4 }.sample(&mut rng)));
+        }
+        let mean = (0..2000).map(|_| Coverage::Poisson { mean: 8.0 }.sample(&mut rng)).sum::<u32>() as f64 / 2000.0;
+        assert!((7.5..8.5).contains(&mean), "poisson mean drifted to {}", mean);
+    }
+
+    #[test]
+    fn corrupt_substitutes_per_matrix_and_keeps_unknown_characters() {
+        let p = ErrorProfile::from_json(r#"{ "substitution": { "A": { "G": 1.0 } } }"#).unwrap();
+        let mut rng = StdRng::seed_from_u64(2);
+        assert_eq!(p.corrupt("ACNAT", &mut rng), "GCNGT");
+    }
+}
@@ -0,0 +1,200 @@
+// src/fasta_header.rs
+// STRUCTURED FASTA HEADERS
+// Strand metadata carried as whitespace-separated tokens after the record ID, so it
+// survives external tools (seqkit, cutadapt, ...) that keep the description line.
+//
+// Format:  >blk{block}_s{shard} helix:v2 tag={tag} frag={tube}/{tubes} crc={crc32 hex}
+// Example: >blk12_s3 helix:v2 tag=photos frag=0/4 crc=ab12cd34
+//
+// Only the ID token is required: bare legacy `>blkN_sM` headers parse as schema v1.
+// Unknown tokens are ignored, so tools may append their own annotations.
+// The block ID exists only here, so the header is authoritative for it. The shard
+// index (DNA address) and payload (in-strand CRC32) are verified in-strand; the
+// remaining fields are hints for routing and inspection.
+
+use std::fmt;
+use crate::provenance::PROVENANCE_HEADER;
+use crate::manifest::MANIFEST_HEADER;
+
+/// Schema version emitted by this build.
+pub const SCHEMA_VERSION: u32 = 2;
+
+const SCHEMA_PREFIX: &str = "helix:v";
+
+/// Parsed metadata of a data strand header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrandHeader {
+    pub block_id: u32,
+    pub shard: usize,
+    /// Header schema version (1 = legacy bare ID).
+    pub schema: u32,
+    /// Molecular tag the primers were derived from.
+    pub tag: Option<String>,
+    /// Archive fragment (tube) `i` of `n`.
+    pub frag: Option<(u16, u16)>,
+    /// CRC32 of the shard bytes, as embedded in the strand.
+    pub crc: Option<u32>,
+}
+
+impl StrandHeader {
+    /// A current-schema header with no optional fields.
+    pub fn new(block_id: u32, shard: usize) -> Self {
+        Self { block_id, shard, schema: SCHEMA_VERSION, tag: None, frag: None, crc: None }
+    }
+
+    /// Parses a header line (leading `>` optional). `None` if it is not a data strand.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut tokens = line.trim_start_matches('>').split_whitespace();
+        let (block, shard) = tokens.next()?.strip_prefix("blk")?.split_once("_s")?;
+        let mut header = Self {
+            block_id: block.parse().ok()?,
+            shard: shard.parse().ok()?,
+            schema: 1,
+            tag: None,
+            frag: None,
+            crc: None,
+        };
+
+        // Malformed values of known keys are dropped rather than rejecting the strand.
+        for token in tokens {
+            if let Some(v) = token.strip_prefix(SCHEMA_PREFIX) {
+                header.schema = v.parse().unwrap_or(header.schema);
+                continue;
+            }
+            match token.split_once('=') {
+                Some(("tag", v)) => header.tag = Some(unescape(v)),
+                Some(("frag", v)) => header.frag = v.split_once('/').and_then(|(i, n)| Some((i.parse().ok()?, n.parse().ok()?))),
+                Some(("crc", v)) => header.crc = u32::from_str_radix(v, 16).ok(),
+                _ => {}
+            }
+        }
+        Some(header)
+    }
+
+    /// Renders the full FASTA header line, including `>` (no newline).
+    pub fn to_line(&self) -> String {
+        format!(">{}", self)
+    }
+}
+
+impl fmt::Display for StrandHeader {
+    /// Header text without the leading `>`. Legacy (v1) headers stay bare.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "blk{}_s{}", self.block_id, self.shard)?;
+        if self.schema < 2 { return Ok(()); }
+
+        write!(f, " {}{}", SCHEMA_PREFIX, self.schema)?;
+        if let Some(tag) = &self.tag {
+            write!(f, " tag={}", escape(tag))?;
+        }
+        if let Some((i, n)) = self.frag {
+            write!(f, " frag={}/{}", i, n)?;
+        }
+        if let Some(crc) = self.crc {
+            write!(f, " crc={:08x}", crc)?;
+        }
+        Ok(())
+    }
+}
+
+/// True if the header belongs to a provenance strand.
+pub fn is_provenance(line: &str) -> bool {
+    record_id(line) == Some(PROVENANCE_HEADER)
+}
+
+/// True if the header belongs to a manifest strand.
+pub fn is_manifest(line: &str) -> bool {
+    record_id(line) == Some(MANIFEST_HEADER)
+}
+
+fn record_id(line: &str) -> Option<&str> {
+    line.trim_start_matches('>').split_whitespace().next()
+}
+
+/// Percent-escapes whitespace and `%` so a value stays a single token.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '%' || c.is_whitespace() {
+            let mut buf = [0u8; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{:02X}", b));
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+        .and_then(|h| std::str::from_utf8(h).ok())
+        .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => { out.push(b); i += 3; }
+            (b, _) => { out.push(b); i += 1; }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_every_field() {
+        let header = StrandHeader {
+            tag: Some("photos".into()),
+            frag: Some((1, 4)),
+            crc: Some(0xab12cd34),
+            ..StrandHeader::new(12, 3)
+        };
+        let line = header.to_line();
+        assert_eq!(line, ">blk12_s3 helix:v2 tag=photos frag=1/4 crc=ab12cd34");
+        assert_eq!(StrandHeader::parse(&line), Some(header));
+    }
+
+    #[test]
+    fn legacy_header_parses_as_v1_and_stays_bare() {
+        let header = StrandHeader::parse(">blk7_s0").unwrap();
+        assert_eq!(header.schema, 1);
+        assert_eq!((header.block_id, header.shard), (7, 0));
+        assert_eq!(header.to_line(), ">blk7_s0");
+    }
+
+    #[test]
+    fn tag_escaping_keeps_a_single_token() {
+        let tag = "100% raw\tdata\u{3000}x";
+        let header = StrandHeader { tag: Some(tag.into()), ..StrandHeader::new(0, 1) };
+        let line = header.to_line();
+        assert_eq!(line.split_whitespace().count(), 3);
+        assert!(line.contains("tag=100%25%20raw%09data%E3%80%80x"));
+        assert_eq!(StrandHeader::parse(&line).unwrap().tag.as_deref(), Some(tag));
+    }
+
+    #[test]
+    fn malformed_tokens_are_dropped() {
+        let header = StrandHeader::parse(">blk2_s5 helix:vX tag=a frag=1/x crc=zz extra=1 note").unwrap();
+        assert_eq!(header.schema, 1);
+        assert_eq!(header.tag.as_deref(), Some("a"));
+        assert_eq!(header.frag, None);
+        assert_eq!(header.crc, None);
+
+        let header = StrandHeader::parse(">blk2_s5 helix:v2 helix:v").unwrap();
+        assert_eq!(header.schema, 2);
+    }
+
+    #[test]
+    fn non_data_records_are_rejected() {
+        assert_eq!(StrandHeader::parse(">helix_manifest"), None);
+        assert_eq!(StrandHeader::parse(">blkX_s1 helix:v2"), None);
+        assert!(is_manifest(&format!(">{} extra", MANIFEST_HEADER)));
+        assert!(is_provenance(&format!(">{}", PROVENANCE_HEADER)));
+    }
+}
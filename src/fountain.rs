@@ -0,0 +1,397 @@
+// src/fountain.rs
+// FOUNTAIN CODES (LT)
+// A rateless alternative to the fixed N+K Reed-Solomon layout, in the style of DNA Fountain.
+// A block is cut into N source symbols; every droplet is the XOR of a pseudo-random subset.
+// The droplet index (the strand address) is the seed: it alone fixes the degree (robust
+// soliton distribution) and the neighbours, so a droplet carries no extra metadata.
+//
+// Decoding is belief propagation (peeling): a degree-1 droplet reveals a source symbol,
+// which is XORed out of every droplet covering it, releasing new degree-1 droplets. If the
+// ripple runs dry early, Gaussian elimination over GF(2) solves what is left (inactivation
+// decoding), so ANY droplet subset of rank N decodes.
+//
+// Droplet count: N + surplus, extended until the full set alone has rank N, so a loss-free
+// archive always decodes. The extension depends only on (N, count), so restore derives the
+// same droplet range from the manifest.
+
+use std::collections::HashMap;
+use std::mem;
+use anyhow::{Result, bail};
+
+/// Upper bound on source symbols per block (bounds the GF(2) rank checks).
+pub const MAX_SOURCE_SYMBOLS: usize = 1024;
+
+/// Default source symbols per block (`--data` under `--codec fountain`). LT needs
+/// ~1.3x droplets at N=10 but under 1.05x from N=50 up.
+pub const DEFAULT_SOURCE_SYMBOLS: usize = 100;
+
+/// Default droplets per source symbol (`--overhead 1.5x`).
+pub const DEFAULT_OVERHEAD: f64 = 1.5;
+
+/// Upper bound on droplets per source symbol.
+pub const MAX_OVERHEAD: f64 = 8.0;
+
+/// Robust soliton parameters. A larger `c` than DNA Fountain's (0.025) suits the small
+/// symbol counts of a single block: more degree-1 droplets keep the ripple alive.
+const ROBUST_C: f64 = 0.1;
+const ROBUST_DELTA: f64 = 0.05;
+
+/// Mixed into every droplet seed ("HELIXFTN").
+const SEED_SALT: u64 = 0x4845_4C49_5846_544E;
+
+#[derive(Debug, Clone)]
+pub struct FountainCodec {
+    source_symbols: usize,
+    /// Sorted source symbol indices XORed into each droplet, by droplet index.
+    neighbors: Vec<Vec<usize>>,
+}
+
+impl FountainCodec {
+    /// Builds the droplet layout for `source_symbols` symbols and at least `surplus` extra droplets.
+    pub fn new(source_symbols: usize, surplus: usize) -> Result<Self> {
+        if source_symbols == 0 || source_symbols > MAX_SOURCE_SYMBOLS {
+            bail!("Fountain codec needs 1..={} source symbols, got {}.", MAX_SOURCE_SYMBOLS, source_symbols);
+        }
+        let cdf = robust_soliton_cdf(source_symbols);
+        let mut basis = Basis::new(source_symbols);
+        let mut neighbors = Vec::new();
+
+        // Extend past N + surplus until the emitted set spans every source symbol.
+        // N extra droplets practically never fail to complete the rank.
+        let limit = source_symbols * 2 + surplus;
+        while neighbors.len() < source_symbols + surplus || basis.rank < source_symbols {
+            if neighbors.len() >= limit {
+                bail!("Fountain layout for {} symbols did not reach full rank in {} droplets.", source_symbols, limit);
+            }
+            let set = droplet_neighbors(neighbors.len(), source_symbols, &cdf);
+            basis.insert(&set);
+            neighbors.push(set);
+        }
+        Ok(Self { source_symbols, neighbors })
+    }
+
+    pub fn source_symbols(&self) -> usize {
+        self.source_symbols
+    }
+
+    /// Droplets emitted per block (valid droplet indices are `0..droplets()`).
+    pub fn droplets(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    /// Cuts `data` into zero-padded source symbols and emits every droplet.
+    pub fn encode_to_droplets(&self, data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let symbol_size = data.len().div_ceil(self.source_symbols);
+        if symbol_size == 0 {
+            bail!("Cannot encode an empty block.");
+        }
+        let mut padded = vec![0u8; symbol_size * self.source_symbols];
+        padded[..data.len()].copy_from_slice(data);
+        let symbols: Vec<Vec<u8>> = padded.chunks_exact(symbol_size).map(|c| c.to_vec()).collect();
+
+        Ok((0..self.droplets()).map(|i| self.droplet(i, &symbols)).collect())
+    }
+
+    /// Re-creates droplet `index` from the source symbols.
+    pub fn droplet(&self, index: usize, symbols: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = vec![0u8; symbols.first().map_or(0, |s| s.len())];
+        for &s in &self.neighbors[index] {
+            xor_into(&mut out, &symbols[s]);
+        }
+        out
+    }
+
+    /// True if the droplets at `indices` determine every source symbol.
+    pub fn is_decodable<'a>(&self, indices: impl IntoIterator<Item = &'a usize>) -> bool {
+        let mut basis = Basis::new(self.source_symbols);
+        for &i in indices {
+            if let Some(set) = self.neighbors.get(i) {
+                basis.insert(set);
+                if basis.rank == self.source_symbols { return true; }
+            }
+        }
+        false
+    }
+
+    /// Recovers the source symbols from any decodable droplet subset (keyed by droplet index).
+    pub fn recover_symbols(&self, droplets: &HashMap<usize, Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+        let symbol_size = droplets.values().map(|d| d.len()).next().unwrap_or(0);
+        if symbol_size == 0 || droplets.values().any(|d| d.len() != symbol_size) {
+            bail!("Droplets have inconsistent or zero length.");
+        }
+        // Cheap symbolic check first: the payload work below is only done once it will succeed.
+        if !self.is_decodable(droplets.keys()) {
+            bail!("Too few independent droplets: {} present, rank below {}.", droplets.len(), self.source_symbols);
+        }
+
+        let mut rows: Vec<(Vec<usize>, Vec<u8>)> = droplets.iter()
+        .filter(|(i, _)| **i < self.droplets())
+        .map(|(i, d)| (self.neighbors[*i].clone(), d.clone()))
+        .collect();
+        let mut covering: Vec<Vec<usize>> = vec![Vec::new(); self.source_symbols];
+        for (r, (set, _)) in rows.iter().enumerate() {
+            for &s in set {
+                covering[s].push(r);
+            }
+        }
+
+        // Belief Propagation: release degree-1 droplets until the ripple runs dry.
+        let mut symbols: Vec<Option<Vec<u8>>> = vec![None; self.source_symbols];
+        let mut ripple: Vec<usize> = (0..rows.len()).filter(|&r| rows[r].0.len() == 1).collect();
+        while let Some(r) = ripple.pop() {
+            if rows[r].0.len() != 1 { continue; }
+            let s = rows[r].0[0];
+            rows[r].0.clear();
+            let payload = mem::take(&mut rows[r].1);
+            if symbols[s].is_some() { continue; }
+
+            for &other in &covering[s] {
+                let (set, data) = &mut rows[other];
+                if let Some(pos) = set.iter().position(|&x| x == s) {
+                    set.swap_remove(pos);
+                    xor_into(data, &payload);
+                    if set.len() == 1 { ripple.push(other); }
+                }
+            }
+            symbols[s] = Some(payload);
+        }
+
+        // Inactivation: Gauss-Jordan over GF(2) on the droplets that never peeled.
+        let unknown: Vec<usize> = (0..self.source_symbols).filter(|&s| symbols[s].is_none()).collect();
+        if !unknown.is_empty() {
+            let mut column = vec![usize::MAX; self.source_symbols];
+            for (c, &s) in unknown.iter().enumerate() {
+                column[s] = c;
+            }
+            let words = unknown.len().div_ceil(64);
+            let mut system: Vec<(Vec<u64>, Vec<u8>)> = rows.into_iter()
+            .filter(|(set, _)| !set.is_empty())
+            .map(|(set, data)| {
+                let mut bits = vec![0u64; words];
+                for s in set {
+                    bits[column[s] / 64] ^= 1 << (column[s] % 64);
+                }
+                (bits, data)
+            })
+            .collect();
+
+            for c in 0..unknown.len() {
+                let has = |bits: &[u64]| bits[c / 64] >> (c % 64) & 1 == 1;
+                let Some(p) = (c..system.len()).find(|&r| has(&system[r].0)) else {
+                    bail!("Droplet system is singular at symbol {}.", unknown[c]);
+                };
+                system.swap(c, p);
+                let pivot = mem::take(&mut system[c]);
+                for (r, (bits, data)) in system.iter_mut().enumerate() {
+                    if r != c && has(bits) {
+                        bits.iter_mut().zip(&pivot.0).for_each(|(a, b)| *a ^= b);
+                        xor_into(data, &pivot.1);
+                    }
+                }
+                system[c] = pivot;
+            }
+            for (c, &s) in unknown.iter().enumerate() {
+                symbols[s] = Some(mem::take(&mut system[c].1));
+            }
+        }
+
+        Ok(symbols.into_iter().flatten().collect())
+    }
+}
+
+/// Rank of a growing droplet set. Lets a decoder wait for the droplet that completes
+/// a block instead of re-checking the whole set on every arrival.
+pub struct RankTracker {
+    basis: Basis,
+}
+
+impl RankTracker {
+    pub fn new(codec: &FountainCodec) -> Self {
+        Self { basis: Basis::new(codec.source_symbols) }
+    }
+
+    /// Adds droplet `index`; returns true once the set determines every source symbol.
+    pub fn insert(&mut self, codec: &FountainCodec, index: usize) -> bool {
+        if let Some(set) = codec.neighbors.get(index) {
+            self.basis.insert(set);
+        }
+        self.basis.rank == codec.source_symbols
+    }
+}
+
+/// Robust soliton distribution (Luby) as a CDF over degrees 1..=k.
+fn robust_soliton_cdf(k: usize) -> Vec<f64> {
+    let kf = k as f64;
+    let r = ROBUST_C * (kf / ROBUST_DELTA).ln() * kf.sqrt();
+    let spike = ((kf / r).floor() as usize).clamp(1, k);
+
+    let mut weights: Vec<f64> = (1..=k).map(|d| {
+        let ideal = if d == 1 { 1.0 / kf } else { 1.0 / (d * (d - 1)) as f64 };
+        let robust = match d {
+            d if d < spike => r / (d as f64 * kf),
+            d if d == spike => r * (r / ROBUST_DELTA).ln() / kf,
+            _ => 0.0,
+        };
+        ideal + robust.max(0.0)
+    }).collect();
+
+    let total: f64 = weights.iter().sum();
+    let mut acc = 0.0;
+    for w in weights.iter_mut() {
+        acc += *w / total;
+        *w = acc;
+    }
+    weights
+}
+
+/// Degree and neighbours of droplet `index`: a pure function of (index, k).
+fn droplet_neighbors(index: usize, k: usize, cdf: &[f64]) -> Vec<usize> {
+    let mut rng = SplitMix64(SEED_SALT ^ index as u64);
+    let degree = (cdf.partition_point(|&p| p < rng.unit()) + 1).min(k);
+
+    // Floyd's algorithm: `degree` distinct symbols out of k.
+    let mut set = Vec::with_capacity(degree);
+    for j in k - degree..k {
+        let t = rng.below(j + 1);
+        set.push(if set.contains(&t) { j } else { t });
+    }
+    set.sort_unstable();
+    set
+}
+
+/// Incremental GF(2) row echelon form over neighbour sets (rank only, no payloads).
+struct Basis {
+    /// Reduced row whose lowest set bit is the key column.
+    pivots: Vec<Option<Vec<u64>>>,
+    rank: usize,
+}
+
+impl Basis {
+    fn new(columns: usize) -> Self {
+        Self { pivots: vec![None; columns], rank: 0 }
+    }
+
+    fn insert(&mut self, set: &[usize]) {
+        let mut bits = vec![0u64; self.pivots.len().div_ceil(64)];
+        for &s in set {
+            bits[s / 64] ^= 1 << (s % 64);
+        }
+        while let Some(lead) = bits.iter().position(|&w| w != 0).map(|w| w * 64 + bits[w].trailing_zeros() as usize) {
+            match &self.pivots[lead] {
+                Some(pivot) => bits.iter_mut().zip(pivot).for_each(|(a, b)| *a ^= b),
+                None => {
+                    self.pivots[lead] = Some(bits);
+                    self.rank += 1;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Seeding is part of the archive format, so it is defined here rather than
+/// borrowed from `rand` (whose generators may change between versions).
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn xor_into(dst: &mut [u8], src: &[u8]) {
+    dst.iter_mut().zip(src).for_each(|(a, b)| *a ^= b);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(len: usize) -> Vec<u8> {
+        let mut rng = SplitMix64(len as u64);
+        (0..len).map(|_| rng.next() as u8).collect()
+    }
+
+    fn shuffled(n: usize, rng: &mut SplitMix64) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..n).collect();
+        for i in (1..n).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+        order
+    }
+
+    #[test]
+    fn random_rank_n_subsets_recover_the_block() {
+        let codec = FountainCodec::new(50, 25).unwrap();
+        let data = block(50 * 16 - 7);
+        let droplets = codec.encode_to_droplets(&data).unwrap();
+        let mut rng = SplitMix64(7);
+
+        for _ in 0..20 {
+            // Stop at the droplet that completes the rank, so peeling usually stalls
+            // and the GF(2) elimination has to finish the block.
+            let mut tracker = RankTracker::new(&codec);
+            let mut subset = HashMap::new();
+            for i in shuffled(codec.droplets(), &mut rng) {
+                subset.insert(i, droplets[i].clone());
+                if tracker.insert(&codec, i) { break; }
+            }
+            assert!(codec.is_decodable(subset.keys()));
+
+            let symbols = codec.recover_symbols(&subset).unwrap();
+            assert_eq!(&symbols.concat()[..data.len()], &data[..]);
+        }
+    }
+
+    #[test]
+    fn rank_deficient_subsets_are_rejected() {
+        let codec = FountainCodec::new(20, 20).unwrap();
+        let droplets = codec.encode_to_droplets(&block(20 * 8)).unwrap();
+
+        // Without a droplet covering symbol 0 the system is singular, however many remain.
+        let subset: HashMap<usize, Vec<u8>> = (0..codec.droplets())
+        .filter(|&i| !codec.neighbors[i].contains(&0))
+        .map(|i| (i, droplets[i].clone()))
+        .collect();
+        assert!(subset.len() >= codec.source_symbols());
+        assert!(!codec.is_decodable(subset.keys()));
+        assert!(codec.recover_symbols(&subset).is_err());
+
+        let mut ragged: HashMap<usize, Vec<u8>> = (0..codec.droplets()).map(|i| (i, droplets[i].clone())).collect();
+        ragged.get_mut(&0).unwrap().pop();
+        assert!(codec.recover_symbols(&ragged).is_err());
+    }
+
+    #[test]
+    fn recorded_surplus_reproduces_the_layout() {
+        // Zero surplus forces the rank extension, which restore must replay exactly.
+        for n in [10, 64, 100] {
+            let codec = FountainCodec::new(n, 0).unwrap();
+            assert!(codec.droplets() > n);
+
+            let replayed = FountainCodec::new(n, codec.droplets() - n).unwrap();
+            assert_eq!(replayed.droplets(), codec.droplets());
+            assert_eq!(replayed.neighbors, codec.neighbors);
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_symbol_counts() {
+        assert!(FountainCodec::new(0, 5).is_err());
+        assert!(FountainCodec::new(MAX_SOURCE_SYMBOLS + 1, 5).is_err());
+        assert!(FountainCodec::new(4, 2).unwrap().encode_to_droplets(&[]).is_err());
+    }
+}
@@ -2,8 +2,23 @@
 pub mod dna_mapper;
 pub mod oligo;
 pub mod rs_engine;
+pub mod fountain;
 pub mod parallel;
+#[cfg(feature = "crypto")]
 pub mod crypto;
 pub mod stream_manager;
+pub mod telemetry;
+pub mod error_profile;
+pub mod tubes;
+pub mod provenance;
+pub mod manifest;
+pub mod fasta_header;
+pub mod consensus;
+pub mod pipeline;
+
+#[cfg(not(feature = "parallel"))]
+mod sequential;
+
+pub use pipeline::{HelixEncoder, HelixDecoder, PipelineConfig, PipelineError, PipelineEvent, StabilityPolicy};
 
 pub const STREAMING_CHUNK_SIZE: usize = 4 * 1024 * 1024;
@@ -1,25 +1,33 @@
 // src/main.rs
 // HELIX: Systems-Level DNA Storage Archiver
 // Entry point for the Command Line Interface.
-// Handles Streaming I/O, Cryptographic Key Derivation, and Pipeline Orchestration.
+// A thin wrapper over `helix::pipeline`: argument resolution, file handling and reports.
 
 mod cli;
 
-use helix::rs_engine::RedundancyManager;
+use helix::rs_engine::{DEFAULT_DATA_SHARDS, DEFAULT_PARITY_SHARDS};
 use helix::parallel::ParallelProcessor;
-use helix::stream_manager::DnaBatchIterator;
-use helix::crypto;
+use helix::stream_manager::{DnaBatchIterator, fill_buffer};
+use helix::pipeline::{self, HelixEncoder, HelixDecoder, PipelineConfig, PipelineError, PipelineEvent, StabilityPolicy};
+use helix::dna_mapper::UnstableStrands;
 use helix::STREAMING_CHUNK_SIZE;
-use helix::oligo::Oligo;
+use helix::oligo::{Oligo, SeedPolicy};
+use helix::telemetry::{DecodeTelemetry, DecodeOutcome};
+use helix::provenance::ProvenanceLedger;
+use helix::fasta_header::{self, StrandHeader};
+use helix::consensus::CoverageStats;
+use helix::fountain::{DEFAULT_OVERHEAD, DEFAULT_SOURCE_SYMBOLS};
+use helix::manifest::{Codec, Manifest, ManifestCollector, MANIFEST_TOTAL_SHARDS, MANIFEST_DATA_SHARDS, MANIFEST_PARITY_SHARDS};
+use helix::error_profile::ErrorProfile;
+use helix::tubes::{TubeMap, TubeEntry, tube_path, map_path};
 use crate::cli::{Cli, Commands};
 
 use clap::Parser;
-use std::fs::{self, File};
-use std::io::{self, Read, Write, BufRead, BufReader};
-use std::collections::{HashMap, BTreeMap};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::io::{self, Write, BufReader, BufWriter};
+use std::collections::{BTreeMap, BTreeSet};
 use anyhow::{Result, Context};
-use rand::RngCore;
-use aes_gcm::{Aes256Gcm, KeyInit, Nonce, aead::Aead};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -39,9 +47,29 @@ fn main() -> Result<()> {
 
     match &cli.command {
         // COMMAND: COMPILE (Archive)
-        Commands::Compile { input, output, tag, password, data, parity, force, primer_fwd, primer_rev } => {
+        Commands::Compile { input, output, tag, password, data, parity, codec, overhead, force, primer_fwd, primer_rev, tubes, seed, copies } => {
             println!("[*] Initializing Streaming Compilation...");
-            println!("[i] Chunk Size: {} MB | RS Config: {}+{}", STREAMING_CHUNK_SIZE / 1024 / 1024, data, parity);
+
+            // Fountain: the droplet surplus comes from --overhead instead of --parity.
+            let (data, parity) = match (codec, overhead) {
+                (Codec::ReedSolomon, None) => {
+                    let (data, parity) = (data.unwrap_or(DEFAULT_DATA_SHARDS), parity.unwrap_or(DEFAULT_PARITY_SHARDS));
+                    println!("[i] Chunk Size: {} MB | RS Config: {}+{}", STREAMING_CHUNK_SIZE / 1024 / 1024, data, parity);
+                    (data, parity)
+                }
+                (Codec::ReedSolomon, Some(_)) => anyhow::bail!("--overhead applies to --codec fountain only."),
+                (Codec::Fountain, _) if parity.is_some() => anyhow::bail!("--parity applies to --codec rs only. Use --overhead to size fountain redundancy."),
+                (Codec::Fountain, o) => {
+                    let data = data.unwrap_or(DEFAULT_SOURCE_SYMBOLS);
+                    let ratio = o.unwrap_or(DEFAULT_OVERHEAD);
+                    println!("[i] Chunk Size: {} MB | Fountain: {} source symbols, {:.2}x overhead",
+                             STREAMING_CHUNK_SIZE / 1024 / 1024, data, ratio);
+                    if data < 50 {
+                        println!("[i] Hint: LT decoding needs ~1.3x droplets at N=10 but under 1.05x from N=50 up. Consider --data {}.", DEFAULT_SOURCE_SYMBOLS);
+                    }
+                    (data, fountain_surplus(data, ratio))
+                }
+            };
 
             // 1. Resolve Biological Addressing (Primers)
             let primers_tuple = Oligo::resolve_primers(tag, primer_fwd.as_deref(), primer_rev.as_deref());
@@ -49,263 +77,319 @@ fn main() -> Result<()> {
             println!("[i] Primers: Fwd={}... Rev={}...", &primers.0[..8.min(primers.0.len())], &primers.1[..8.min(primers.1.len())]);
 
             let input_file = File::open(input).context(format!("Failed to open input: {}", input))?;
-            let mut reader = BufReader::new(input_file);
-            let mut output_file = File::create(output).context(format!("Failed to create output: {}", output))?;
-
-            // 2. Pre-calculate Master Key (If Encryption Enabled)
-            let mut master_key = [0u8; 32];
-            let mut global_salt = [0u8; 16]; // Used to salt the Master Key
-            let has_password = password.is_some();
-
-            if let Some(pass) = password {
-                print!("[*] Deriving Argon2id Master Key (this takes a moment)... ");
-                io::stdout().flush()?;
-
-                rand::thread_rng().fill_bytes(&mut global_salt);
-                master_key = crypto::derive_master_key(pass, &global_salt)?;
-
-                println!("Done.");
-            }
-
-            // 3. Begin Streaming Pipeline
-            let mut buffer = vec![0u8; STREAMING_CHUNK_SIZE];
-            let mut block_id = 0u32;
-            let mut total_bytes = 0u64;
-            let mut total_encoded_bytes = 0u64;
-            let max_retries = 5;
-
-            loop {
-                // Read Chunk (Input IO)
-                let bytes_read = reader.read(&mut buffer)?;
-                if bytes_read == 0 { break; }
-
-                let chunk_data = &buffer[..bytes_read];
-                total_bytes += bytes_read as u64;
-
-                // Step A: Compression (Zstd) - Deterministic, do once per block
-                let compressed_payload = zstd::encode_all(chunk_data, 3)?;
-
-                // RETRY LOOP: Salt Rotation
-                // If the resulting DNA is unstable (high GC/bad Tm), we re-roll the Block Salt.
-                // This changes the encryption ciphertext, which changes the DNA sequence.
-                let mut attempts = 0;
-                loop {
-                    attempts += 1;
-
-                    // Step B: Encryption (HKDF Session Key -> AES-256-GCM)
-                    let mut payload = compressed_payload.clone();
-                    let mut nonce_bytes = [0u8; 12];
-                    let mut block_salt = [0u8; 16];
-
-                    // Generate FRESH salts for this attempt
-                    rand::thread_rng().fill_bytes(&mut nonce_bytes);
-                    rand::thread_rng().fill_bytes(&mut block_salt);
-
-                    if has_password {
-                        let session_key = crypto::derive_session_key(&master_key, &block_salt);
-                        let cipher = Aes256Gcm::new(&session_key);
-                        let nonce = Nonce::from_slice(&nonce_bytes);
-
-                        payload = cipher.encrypt(nonce, payload.as_ref())
-                        .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
-                    }
+            let input_len = input_file.metadata()?.len();
+            let reader = BufReader::new(input_file);
 
-                    // Step C: Header Construction
-                    // Format: [OrigLen 8] [EncLen 8] [GlobalSalt 16] [BlockSalt 16] [Nonce 12] [Payload...]
-                    let mut data_to_encode = (bytes_read as u64).to_be_bytes().to_vec();
-                    data_to_encode.extend_from_slice(&(payload.len() as u64).to_be_bytes());
-                    data_to_encode.extend_from_slice(&global_salt);
-                    data_to_encode.extend_from_slice(&block_salt);
-                    data_to_encode.extend_from_slice(&nonce_bytes);
-                    data_to_encode.extend_from_slice(&payload);
-
-                    // Step D: Reed-Solomon Encoding
-                    let rs = RedundancyManager::new(*data, *parity)?;
-                    let shards = rs.encode_to_shards(&data_to_encode)?;
-
-                    // Step E: DNA Transcoding & Analysis (Parallel)
-                    let results = ParallelProcessor::process_block(block_id, shards, primers);
-
-                    // Step F: Stats & Stability Check
-                    let mut unstable_count = 0;
-                    let mut block_gc_sum = 0.0;
-                    let mut block_tm_sum = 0.0;
-
-                    for res in &results {
-                        if !res.stability.is_stable { unstable_count += 1; }
-                        block_gc_sum += res.stability.gc_content;
-                        block_tm_sum += res.stability.melting_temp;
+            // Tube Partitioning: contiguous block ranges, one orthogonal primer pair per tube.
+            // A single tube keeps the classic layout (one FASTA, tag primers, no map).
+            let tube_count = *tubes as usize;
+            let config = PipelineConfig {
+                codec: *codec,
+                data_shards: data,
+                parity_shards: parity,
+                primer_sets: Oligo::orthogonal_primer_set(primers, tube_count)?,
+                password: password.clone(),
+                seeds: *seed,
+                stability: StabilityPolicy { force: *force, ..StabilityPolicy::default() },
+                copies: *copies as usize,
+                ..PipelineConfig::new(tag)
+            };
+            config.validate()?;
+
+            let output_paths: Vec<String> = if tube_count == 1 {
+                vec![output.clone()]
+            } else {
+                (0..tube_count).map(|t| tube_path(output, t)).collect()
+            };
+            let mut output_files = output_paths.iter()
+            .map(|p| File::create(p).map(BufWriter::new).context(format!("Failed to create output: {}", p)))
+            .collect::<Result<Vec<_>>>()?;
+
+            let seed = *seed;
+            let mut encoder = HelixEncoder::new(config)
+            .with_file_name(Path::new(input).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default())
+            .with_input_len(input_len)
+            .on_progress(move |event| match event {
+                PipelineEvent::ArchiveStarted { archive_id, tool_version, blocks } => {
+                    println!("[i] Archive ID: {} (helix v{})", archive_id, tool_version);
+                    println!("[i] Trellis Seeds: {}", seed);
+                    if let Some(blocks) = blocks.filter(|_| tube_count > 1) {
+                        println!("[i] Tubes: {} (~{} blocks total)", tube_count, blocks);
                     }
-
-                    let avg_gc = block_gc_sum / (data + parity) as f64;
-                    let avg_tm = block_tm_sum / (data + parity) as f64;
-
+                }
+                PipelineEvent::DerivingKey => {
+                    print!("[*] Deriving Argon2id Master Key (this takes a moment)... ");
+                    io::stdout().flush().ok();
+                }
+                PipelineEvent::KeyDerived => println!("Done."),
+                PipelineEvent::BlockAttempt { block_id, bytes, avg_gc, avg_tm, attempt } => {
                     print!("\r    -> Processing Block {} ({} bytes) [GC: {:.1}% | Tm: {:.1}°C] [Try {}]... ",
-                           block_id, bytes_read, avg_gc, avg_tm, attempts);
-                    io::stdout().flush()?;
-
-                    // Decision Logic
-                    if unstable_count == 0 {
-                        // Success! Write to disk.
-                        total_encoded_bytes += data_to_encode.len() as u64;
-                        for res in results {
-                            output_file.write_all(res.fasta_entry.as_bytes())?;
-                        }
-                        break;
-                    } else {
-                        // Failure case
-                        if attempts >= max_retries {
-                            if *force {
-                                println!(" [WARNING: {} unstable strands. Force override used.] ", unstable_count);
-                                total_encoded_bytes += data_to_encode.len() as u64;
-                                for res in results {
-                                    output_file.write_all(res.fasta_entry.as_bytes())?;
-                                }
-                                break;
-                            } else {
-                                anyhow::bail!("\n[✘] SAFETY HALT in Block {}: {} unstable strands after {} retries. Use --force to override.", block_id, unstable_count, attempts);
-                            }
-                        }
-                        // If we have retries left, loop again. The new salt will change the DNA.
-                    }
+                           block_id, bytes, avg_gc, avg_tm, attempt);
+                    io::stdout().flush().ok();
+                }
+                PipelineEvent::PayloadCollisions { block_id, pairs } => {
+                    println!("\n    [!] WARNING: Block {} has {} identical shard pair(s) {:?}. Input may be degenerate.",
+                             block_id, pairs.len(), pairs);
+                }
+                PipelineEvent::ForcedUnstable { unstable, .. } => {
+                    println!(" [WARNING: {} unstable strands. Force override used.] ", unstable);
                 }
-                block_id += 1;
+                _ => {}
+            });
+            let summary = encoder.compile_tubes(reader, &mut output_files).map_err(decorate_error)?;
+            let provenance = &summary.provenance;
+
+            if let Some(file_len) = summary.manifest.file_len.filter(|&l| l != summary.total_bytes) {
+                println!("\n[!] WARNING: Input changed while compiling ({} bytes read, manifest records {}).", summary.total_bytes, file_len);
             }
 
             println!("\n[✔] Compilation Finished.");
             println!("--------------------------------------------------");
-            println!("    Total Input:     {} bytes", total_bytes);
-            println!("    Encoded Data:    {} bytes (before redundancy)", total_encoded_bytes);
-            println!("    Blocks Created:  {}", block_id);
-            if total_bytes > 0 {
-                println!("    Effective Ratio: {:.2}% (Input vs Encoded)", (total_encoded_bytes as f64 / total_bytes as f64) * 100.0);
+            println!("    Total Input:     {} bytes", summary.total_bytes);
+            println!("    Encoded Data:    {} bytes (before redundancy)", summary.encoded_bytes);
+            println!("    Blocks Created:  {}", summary.blocks);
+            if *codec == Codec::Fountain {
+                println!("    Droplets:        {} per block ({} source symbols)", data + summary.manifest.parity_shards, data);
+            }
+            if *copies > 1 {
+                println!("    Strands Written: {} ({} physical copies each)", summary.strands, copies);
+            } else {
+                println!("    Strands Written: {}", summary.strands);
+            }
+            if summary.total_bytes > 0 {
+                println!("    Effective Ratio: {:.2}% (Input vs Encoded)", (summary.encoded_bytes as f64 / summary.total_bytes as f64) * 100.0);
+            }
+            println!("    Manifest:        {} strands per tube (RS {}+{})", MANIFEST_TOTAL_SHARDS, MANIFEST_DATA_SHARDS, MANIFEST_PARITY_SHARDS);
+            println!("    Archive ID:      {}", provenance.uuid());
+            println!("    Created:         {}", provenance.created_utc());
+            if tube_count == 1 {
+                println!("    Output File:     {}", output);
+            } else {
+                let map = TubeMap {
+                    archive_id: provenance.uuid(),
+                    tag: tag.clone(),
+                    codec: *codec,
+                    data_shards: data,
+                    parity_shards: summary.manifest.parity_shards,
+                    seed_policy: seed.to_string(),
+                    tubes: output_paths.iter().zip(encoder.config().primer_sets.iter()).zip(summary.tube_blocks)
+                    .enumerate()
+                    .map(|(t, ((file, (fp, rp)), blocks))| TubeEntry {
+                        tube: t,
+                        file: file.clone(),
+                        primer_fwd: fp.clone(),
+                        primer_rev: rp.clone(),
+                        blocks,
+                    })
+                    .collect(),
+                };
+                let map_file = map_path(output);
+                map.save(&map_file)?;
+
+                for entry in &map.tubes {
+                    println!("    Tube {:<2}         {} ({} blocks, Fwd={}...)", entry.tube, entry.file, entry.blocks.len(), &entry.primer_fwd[..8.min(entry.primer_fwd.len())]);
+                }
+                println!("    Tube Map:        {}", map_file);
             }
-            println!("    Output File:     {}", output);
             println!("--------------------------------------------------");
         }
 
         // COMMAND: RESTORE (Decode)
-        Commands::Restore { input, output, tag, password, data, parity, primer_fwd, primer_rev } => {
-            println!("[*] Reading DNA Stream from {}...", input);
-
-            let primers_tuple = Oligo::resolve_primers(tag, primer_fwd.as_deref(), primer_rev.as_deref());
-            let primers = (primers_tuple.0.as_str(), primers_tuple.1.as_str());
-            println!("[i] Primers: Fwd={}... Rev={}...", &primers.0[..8.min(primers.0.len())], &primers.1[..8.min(primers.1.len())]);
-
-            let input_file = File::open(&input).context("Failed to open DNA file")?;
-            let input_size = input_file.metadata()?.len();
+        Commands::Restore { input, output, tag, password, data, parity, codec, primer_fwd, primer_rev, map, seed, min_coverage } => {
+            println!("[*] Reading DNA Stream from {}...", input.join(", "));
+
+            // Multi-Tube: the map supplies every tube's primers and the RS geometry.
+            let tube_map = map.as_deref().map(TubeMap::load).transpose()?;
+            let primer_sets = match &tube_map {
+                Some(m) => m.primer_sets(),
+                None => vec![Oligo::resolve_primers(tag, primer_fwd.as_deref(), primer_rev.as_deref())],
+            };
+            if let Some(m) = &tube_map {
+                println!("[i] Tube Map: {} tubes", m.tubes.len());
+            }
+            for (fp, rp) in &primer_sets {
+                Oligo::validate_primers((fp, rp))?;
+                println!("[i] Primers: Fwd={}... Rev={}...", &fp[..8.min(fp.len())], &rp[..8.min(rp.len())]);
+            }
 
-            let reader = BufReader::new(input_file);
-            let mut output_file = File::create(output).context("Failed to create output file")?;
+            // Metadata Pre-Scan: provenance stamp (seed policy) and manifest (codec parameters).
+            let (stamp, manifest) = pipeline::scan_metadata(open_inputs(input)?, &primer_sets)?;
+            if let Some(m) = &manifest {
+                print_manifest(m);
+            }
 
-            // Streaming State
-            let mut active_blocks: HashMap<u32, HashMap<usize, Vec<u8>>> = HashMap::new();
-            let mut decoded_buffer: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
-            let mut next_expected_block = 0u32;
-            let mut shards_found = 0;
-            let mut blocks_recovered = 0;
-
-            // Cache for Master Key to avoid re-deriving per block
-            let mut cached_master_key: Option<[u8; 32]> = None;
-
-            let mut lines = reader.lines();
-            while let Some(Ok(header)) = lines.next() {
-                if !header.starts_with('>') { continue; }
-
-                if let Some(Ok(dna)) = lines.next() {
-                    // Parallel Parser: Decodes trellis, verifies CRC32
-                    if let Some((blk_id, idx, data_shard)) = ParallelProcessor::parse_strand(&header, &dna, primers) {
-                        shards_found += 1;
-
-                        if blk_id >= next_expected_block {
-                            active_blocks.entry(blk_id).or_default().insert(idx, data_shard);
-
-                            let block_shards = active_blocks.get(&blk_id).unwrap();
-
-                            // Check if we have enough shards to trigger Reed-Solomon
-                            if block_shards.len() >= *data {
-                                let mut rs_shards = Vec::new();
-                                for i in 0..(*data + *parity) {
-                                    rs_shards.push(block_shards.get(&i).cloned());
-                                }
-
-                                let rs = RedundancyManager::new(*data, *parity)?;
-                                if let Ok(raw_block) = rs.recover_file(rs_shards) {
-                                    // Parse Binary Header
-                                    // [OrigLen 8] [EncLen 8] [GlobalSalt 16] [BlockSalt 16] [Nonce 12] [Payload...]
-                                    let orig_len = u64::from_be_bytes(raw_block[0..8].try_into()?) as usize;
-                                    let enc_len = u64::from_be_bytes(raw_block[8..16].try_into()?) as usize;
-
-                                    let global_salt = &raw_block[16..32];
-                                    let block_salt = &raw_block[32..48];
-                                    let nonce_bytes = &raw_block[48..60];
-                                    let mut payload = raw_block[60..60 + enc_len].to_vec();
-
-                                    // Decryption
-                                    if let Some(pass) = password {
-                                        // Optimization: Only derive Master Key if needed
-                                        if cached_master_key.is_none() {
-                                            print!("[*] Deriving Master Key for decryption... ");
-                                            io::stdout().flush()?;
-                                            cached_master_key = Some(crypto::derive_master_key(pass, global_salt)?);
-                                            println!("Done.");
-                                        }
-
-                                        let master_key = cached_master_key.unwrap();
-                                        let session_key = crypto::derive_session_key(&master_key, block_salt);
-
-                                        let cipher = Aes256Gcm::new(&session_key);
-                                        let nonce = Nonce::from_slice(nonce_bytes);
-                                        match cipher.decrypt(nonce, payload.as_ref()) {
-                                            Ok(p) => payload = p,
-                                            Err(_) => {
-                                                anyhow::bail!("\n[!] SECURITY ERROR: Decryption failed for Block {}.", blk_id);
-                                            }
-                                        }
-                                    }
-
-                                    // Decompression
-                                    let decompressed = zstd::decode_all(&*payload)?;
-                                    let final_data = decompressed[..orig_len].to_vec();
-
-                                    decoded_buffer.insert(blk_id, final_data);
-                                    active_blocks.remove(&blk_id);
-                                    blocks_recovered += 1;
-
-                                    print!("\r    -> Recovered Block {} ({} bytes)... ", blk_id, orig_len);
-                                    io::stdout().flush()?;
-
-                                    // Write ordered blocks to disk
-                                    while let Some(ready_data) = decoded_buffer.remove(&next_expected_block) {
-                                        output_file.write_all(&ready_data)?;
-                                        next_expected_block += 1;
-                                    }
-                                }
-                            }
-                        }
+            // Trellis Seeds: explicit flag > tube map > provenance stamp > chained (pre-stamp archives).
+            let (seeds, seed_source) = match (seed, &tube_map, &stamp) {
+                (Some(s), _, _) => (*s, "flag"),
+                (None, Some(m), _) => (m.seeds()?, "tube map"),
+                (None, None, Some(p)) => (p.seed_policy, "provenance"),
+                (None, None, None) => (SeedPolicy::Chained, "default"),
+            };
+            println!("[i] Trellis Seeds: {} (from {})", seeds, seed_source);
+
+            // Codec: explicit flag > tube map > manifest > Reed-Solomon (pre-manifest archives).
+            let codec = match (codec, &tube_map, &manifest) {
+                (Some(c), _, _) => *c,
+                (None, Some(m), _) => m.codec,
+                (None, None, Some(m)) => m.codec,
+                (None, None, None) => Codec::ReedSolomon,
+            };
+            // Geometry: explicit flags > tube map > manifest > the codec's compile defaults.
+            let (data, parity, geometry_source) = match (data, parity, &tube_map, &manifest) {
+                (Some(d), Some(p), _, _) => (*d, *p, "flags"),
+                (d, p, Some(m), _) => (d.unwrap_or(m.data_shards), p.unwrap_or(m.parity_shards), "tube map"),
+                (d, p, None, Some(m)) => (d.unwrap_or(m.data_shards), p.unwrap_or(m.parity_shards), "manifest"),
+                (d, p, None, None) => match codec {
+                    Codec::ReedSolomon => (d.unwrap_or(DEFAULT_DATA_SHARDS), p.unwrap_or(DEFAULT_PARITY_SHARDS), "default"),
+                    Codec::Fountain => {
+                        let d = d.unwrap_or(DEFAULT_SOURCE_SYMBOLS);
+                        (d, p.unwrap_or_else(|| fountain_surplus(d, DEFAULT_OVERHEAD)), "default")
                     }
+                },
+            };
+            let geometry = match codec {
+                Codec::ReedSolomon => "RS Config",
+                Codec::Fountain => "Fountain Config",
+            };
+            println!("[i] {}: {}+{} (from {})", geometry, data, parity, geometry_source);
+            if let Some(m) = manifest.as_ref().filter(|m| m.codec != codec) {
+                println!("[!] WARNING: Codec '{}' overrides the manifest's '{}'.", codec, m.codec);
+            }
+            if let Some(m) = manifest.as_ref().filter(|m| (m.data_shards, m.parity_shards) != (data, parity)) {
+                println!("[!] WARNING: {} {}+{} overrides the manifest's {}+{}.", geometry, data, parity, m.data_shards, m.parity_shards);
+            }
+
+            // Encryption & Compression follow the manifest when there is one.
+            let password = match (&manifest, password) {
+                (Some(m), None) if m.encrypted => {
+                    anyhow::bail!("[!] ENCRYPTED ARCHIVE: The manifest marks this archive as encrypted. Supply --password.");
+                }
+                (Some(m), Some(_)) if !m.encrypted => {
+                    println!("[i] Archive is not encrypted; ignoring --password.");
+                    None
+                }
+                (_, p) => p.clone(),
+            };
+            let defaults = PipelineConfig::new(tag);
+            let compression = manifest.as_ref().map_or(defaults.compression, |m| m.compression);
+
+            // A directory output takes the original file name (directories stripped).
+            let output: PathBuf = if Path::new(output).is_dir() {
+                match manifest.as_ref().and_then(|m| Path::new(&m.file_name).file_name()) {
+                    Some(name) => Path::new(output).join(name),
+                    None => anyhow::bail!("Output {} is a directory, but the archive records no file name.", output),
+                }
+            } else {
+                PathBuf::from(output)
+            };
+
+            let config = PipelineConfig {
+                codec,
+                data_shards: data,
+                parity_shards: parity,
+                primer_sets,
+                password,
+                seeds,
+                compression,
+                min_coverage: *min_coverage as usize,
+                ..defaults
+            };
+
+            let mut input_size = 0u64;
+            for path in input {
+                input_size += std::fs::metadata(path).context(format!("Failed to open DNA file: {}", path))?.len();
+            }
+            let readers = open_inputs(input)?;
+            let output_file = File::create(&output).context("Failed to create output file")?;
+
+            let summary = HelixDecoder::new(config)
+            .on_progress(|event| match event {
+                PipelineEvent::DerivingKey => {
+                    print!("[*] Deriving Master Key for decryption... ");
+                    io::stdout().flush().ok();
+                }
+                PipelineEvent::KeyDerived => println!("Done."),
+                PipelineEvent::BlockRecovered { block_id, bytes } => {
+                    print!("\r    -> Recovered Block {} ({} bytes)... ", block_id, bytes);
+                    io::stdout().flush().ok();
                 }
+                _ => {}
+            })
+            .restore_tubes(readers, BufWriter::new(output_file))
+            .map_err(decorate_error)?;
+            let telemetry = &summary.telemetry;
+            let blocks_recovered = summary.recovered_blocks.len();
+
+            println!("\n\n[+] Stream processing done. Found {} valid shards.", summary.shards_found);
+            print_decode_report(telemetry);
+            print_coverage_report(&summary.coverage, *min_coverage);
+            print_provenance(&summary.provenance);
+            if summary.provenance.runs().any(|(p, _)| p.seed_policy != seeds) {
+                println!("[!] SEED MISMATCH: Decoded with '{}' but the archive records a different policy.", seeds);
             }
 
-            println!("\n\n[+] Stream processing done. Found {} valid shards.", shards_found);
+            if summary.late_shards > 0 {
+                println!("[i] Late Arrivals: {} surplus shards for already recovered blocks ({} consistent).",
+                         summary.late_shards, summary.late_shards - summary.late_conflicts);
+            }
+            if !summary.conflicting_blocks.is_empty() {
+                println!("[!] CONFLICT: Blocks {:?} have surplus shards that disagree with the recovered version.", summary.conflicting_blocks);
+                println!("    The pool may contain more than one archive under the same primers. Output uses the first complete version.");
+            }
 
             // Detect Empty vs Invalid Archive
-            if shards_found == 0 && input_size > 0 {
+            if summary.shards_found == 0 && telemetry.provenance == 0 && telemetry.manifest == 0 && input_size > 0 {
                 anyhow::bail!("[!] MATCH FAILURE: File contains data, but no strands matched the provided Primers/Tag. Check your credentials.");
             }
+            if blocks_recovered == 0 && telemetry.rejected() > 0 && summary.partial_blocks.is_empty() {
+                anyhow::bail!("[!] NO DATA RECOVERED: {} strands were rejected and no data shard survived decoding.", telemetry.rejected());
+            }
 
-            if !active_blocks.is_empty() {
-                let corrupted_ids: Vec<_> = active_blocks.keys().collect();
-                println!("\n[!] PARTIAL DATA: Found fragments of blocks {:?} but not enough to recover.", corrupted_ids);
+            if !summary.partial_blocks.is_empty() {
+                println!("\n[!] PARTIAL DATA: Found fragments of blocks {:?} but not enough to recover.", summary.partial_blocks);
                 anyhow::bail!("[!] CATASTROPHIC FAILURE: Insufficient redundancy. Data is lost.");
             }
 
-            if !decoded_buffer.is_empty() {
-                let stuck_ids: Vec<_> = decoded_buffer.keys().collect();
-                anyhow::bail!("\n[!] SEQUENCE GAP: Recovered blocks {:?} but missing preceding Block {}. Stream is broken.", stuck_ids, next_expected_block);
+            if let Some(m) = &tube_map {
+                let missing = m.missing_blocks(|b| summary.recovered_blocks.contains(&b));
+                if !missing.is_empty() {
+                    for (entry, blocks) in &missing {
+                        println!("[!] MISSING TUBE DATA: Tube {} ({}) is missing blocks {:?}.", entry.tube, entry.file, blocks);
+                    }
+                    anyhow::bail!("[!] INCOMPLETE ARCHIVE: {} tube(s) not fully recovered. Supply every tube listed in the map.", missing.len());
+                }
             }
 
-            println!("[✔] Restoration Complete: {} blocks written to {}.", blocks_recovered, output);
+            // Streamed archives record no block count; the sequence gap check below still applies.
+            if let Some((m, total_blocks)) = manifest.as_ref().and_then(|m| Some((m, m.total_blocks?))) {
+                let missing: Vec<u32> = (0..total_blocks).filter(|b| !summary.recovered_blocks.contains(b)).collect();
+                // Only the final block is ever short: if the stream ends on one, the input shrank
+                // after its length was recorded and the trailing blocks were never written.
+                let shrank = summary.pending_blocks.is_empty()
+                    && missing.iter().all(|&b| b >= summary.next_block)
+                    && summary.bytes_written < summary.next_block as u64 * m.chunk_size as u64;
+                if !missing.is_empty() && shrank {
+                    println!("[!] WARNING: The manifest lists {} blocks, but the input ended after {} blocks (it shrank while compiling).",
+                             total_blocks, summary.next_block);
+                } else if !missing.is_empty() {
+                    println!("[!] MISSING BLOCKS: {} of {} blocks were never recovered (first: {:?}).",
+                             missing.len(), total_blocks, &missing[..missing.len().min(10)]);
+                    if m.tubes > 1 && tube_map.is_none() {
+                        println!("[i] The archive spans {} tubes. Supply every tube file and its --map.", m.tubes);
+                    }
+                    anyhow::bail!("[!] INCOMPLETE ARCHIVE: The manifest lists {} blocks; only {} were recovered.", total_blocks, blocks_recovered);
+                }
+            }
+
+            if !summary.pending_blocks.is_empty() {
+                anyhow::bail!("\n[!] SEQUENCE GAP: Recovered blocks {:?} but missing preceding Block {}. Stream is broken.", summary.pending_blocks, summary.next_block);
+            }
+
+            if let Some(file_len) = manifest.as_ref().and_then(|m| m.file_len).filter(|&l| l != summary.bytes_written) {
+                println!("[!] WARNING: Restored {} bytes, but the manifest records {}.", summary.bytes_written, file_len);
+            }
+
+            println!("[✔] Restoration Complete: {} blocks written to {}.", blocks_recovered, output.display());
         }
 
         // COMMAND: SEARCH (In-Silico PCR)
@@ -339,13 +423,136 @@ fn main() -> Result<()> {
             println!("[+] Amplified {} matching strands to {}.", total_matches, output);
         }
 
+        // COMMAND: INSPECT (Provenance & Stats)
+        Commands::Inspect { input, tag, primer_fwd, primer_rev, map } => {
+            println!("[*] Inspecting {}...", input);
+
+            let primer_sets = match map {
+                Some(m) => TubeMap::load(m)?.primer_sets(),
+                None => vec![Oligo::resolve_primers(tag, primer_fwd.as_deref(), primer_rev.as_deref())],
+            };
+
+            let input_file = File::open(input).context(format!("Failed to open input: {}", input))?;
+            let batcher = DnaBatchIterator::new(BufReader::new(input_file), 5000, 32 * 1024 * 1024);
+
+            let mut total_records = 0u64;
+            let mut data_strands = 0u64;
+            let mut blocks: BTreeSet<u32> = BTreeSet::new();
+            let mut schemas: BTreeMap<u32, u64> = BTreeMap::new();
+            let mut tags: BTreeSet<String> = BTreeSet::new();
+            let mut ledger = ProvenanceLedger::new();
+            let mut manifest = ManifestCollector::new();
+
+            for batch_result in batcher {
+                for (header, dna) in batch_result? {
+                    total_records += 1;
+
+                    if fasta_header::is_provenance(&header) {
+                        if let DecodeOutcome::Provenance(p) = ParallelProcessor::decode_strand_any(&header, &dna, &primer_sets, SeedPolicy::Chained) {
+                            ledger.record(&p);
+                        }
+                    } else if fasta_header::is_manifest(&header) {
+                        if let DecodeOutcome::Manifest { index, data } = ParallelProcessor::decode_strand_any(&header, &dna, &primer_sets, SeedPolicy::Chained) {
+                            manifest.insert(index, data)?;
+                        }
+                    } else if let Some(h) = StrandHeader::parse(&header) {
+                        data_strands += 1;
+                        blocks.insert(h.block_id);
+                        *schemas.entry(h.schema).or_default() += 1;
+                        tags.extend(h.tag);
+                    }
+                }
+            }
+
+            println!("--------------------------------------------------");
+            println!("    Records:         {}", total_records);
+            println!("    Data Strands:    {}", data_strands);
+            match (blocks.first(), blocks.last()) {
+                (Some(first), Some(last)) => println!("    Blocks Present:  {} (IDs {}..={})", blocks.len(), first, last),
+                _ => println!("    Blocks Present:  0"),
+            }
+            if !schemas.is_empty() {
+                let listed: Vec<String> = schemas.iter().map(|(v, n)| format!("v{} ({})", v, n)).collect();
+                println!("    Header Schema:   {}", listed.join(", "));
+            }
+            if !tags.is_empty() {
+                println!("    Tags:            {}", tags.into_iter().collect::<Vec<_>>().join(", "));
+            }
+            println!("--------------------------------------------------");
+            print_provenance(&ledger);
+            match manifest.manifest() {
+                Some(m) => print_manifest(m),
+                None => println!("[i] Manifest: none found (pre-manifest archive or too many strands lost)."),
+            }
+        }
+
+        // COMMAND: COMPARE (Verification)
+        Commands::Compare { original, restored } => {
+            println!("[*] Comparing {} against {}...", restored, original);
+
+            let orig_file = File::open(original).context(format!("Failed to open original: {}", original))?;
+            let rest_file = File::open(restored).context(format!("Failed to open restored: {}", restored))?;
+            let orig_len = orig_file.metadata()?.len();
+            let rest_len = rest_file.metadata()?.len();
+
+            let mut orig_reader = BufReader::new(orig_file);
+            let mut rest_reader = BufReader::new(rest_file);
+            let mut orig_buf = vec![0u8; 64 * 1024];
+            let mut rest_buf = vec![0u8; 64 * 1024];
+            let mut offset = 0u64;
+            let mut divergence: Option<u64> = None;
+
+            // Stream both files in lockstep; stop at the first differing byte or shorter EOF.
+            loop {
+                let n_orig = fill_buffer(&mut orig_reader, &mut orig_buf)?;
+                let n_rest = fill_buffer(&mut rest_reader, &mut rest_buf)?;
+                let common = n_orig.min(n_rest);
+
+                if let Some(pos) = orig_buf[..common].iter().zip(&rest_buf[..common]).position(|(a, b)| a != b) {
+                    divergence = Some(offset + pos as u64);
+                    break;
+                }
+                if n_orig != n_rest {
+                    divergence = Some(offset + common as u64);
+                    break;
+                }
+                if n_orig == 0 { break; }
+                offset += common as u64;
+            }
+
+            println!("--------------------------------------------------");
+            println!("    Original Size:   {} bytes", orig_len);
+            println!("    Restored Size:   {} bytes", rest_len);
+
+            match divergence {
+                None => {
+                    println!("--------------------------------------------------");
+                    println!("[✔] MATCH: Files are identical.");
+                }
+                Some(pos) => {
+                    let block = pos / STREAMING_CHUNK_SIZE as u64;
+                    let block_offset = pos % STREAMING_CHUNK_SIZE as u64;
+                    println!("    First Diverges:  byte {} (0x{:X})", pos, pos);
+                    println!("    Falls In:        Block {} (+{} bytes)", block, block_offset);
+                    println!("--------------------------------------------------");
+                    anyhow::bail!("[✘] MISMATCH: Restored file diverges from the original at byte {} (Block {}).", pos, block);
+                }
+            }
+        }
+
         // COMMAND: SIMULATE (Mutation & Decay)
-        Commands::Simulate { input, output, dropout, mutation } => {
-            println!("[*] Simulating {}% dropout and {:.2}% mutation (Smart Stream)...", dropout, mutation * 100.0);
+        Commands::Simulate { input, output, dropout, mutation, profile, rng_seed } => {
+            let error_profile = profile.as_deref().map(ErrorProfile::load).transpose()?;
+
+            match &error_profile {
+                Some(p) => println!("[*] Simulating profile '{}': {:.1}% dropout, {:.2}% ins, {:.2}% del, coverage {:?} (Smart Stream)...",
+                                    p.name, p.dropout * 100.0, p.insertion * 100.0, p.deletion * 100.0, p.coverage),
+                None => println!("[*] Simulating {}% dropout and {:.2}% mutation (Smart Stream)...", dropout, mutation * 100.0),
+            }
 
-            let input_file = File::open(&input).context(format!("Failed to open input: {}", input))?;
+            let input_file = File::open(input).context(format!("Failed to open input: {}", input))?;
             let reader = BufReader::new(input_file);
-            let mut output_file = File::create(&output).context(format!("Failed to create output: {}", output))?;
+            let mut output_file = File::create(output).context(format!("Failed to create output: {}", output))?;
 
             let dropout_rate = *dropout as f64 / 100.0;
             let mut total_strands = 0;
@@ -358,10 +565,15 @@ fn main() -> Result<()> {
 
             for batch_result in batcher {
                 let batch = batch_result?;
+                // Seeds continue across batches, one per strand of the input.
+                let batch_seed = rng_seed.map(|s| s.wrapping_add(total_strands as u64));
                 total_strands += batch.len();
 
                 // Process batch in parallel
-                let survivors = ParallelProcessor::process_decay_batch(batch, dropout_rate, *mutation);
+                let survivors = match &error_profile {
+                    Some(p) => ParallelProcessor::process_profile_batch(batch, p, batch_seed),
+                    None => ParallelProcessor::process_decay_batch(batch, dropout_rate, *mutation),
+                };
                 kept_strands += survivors.len();
 
                 // Stream to disk immediately
@@ -371,8 +583,105 @@ fn main() -> Result<()> {
                 }
             }
 
-            println!("[!] Simulation Complete. Processed {} strands. Surviving: {} (in {}).", total_strands, kept_strands, output);
+            if error_profile.is_some() {
+                println!("[!] Simulation Complete. Processed {} strands. Reads emitted: {} (in {}).", total_strands, kept_strands, output);
+            } else {
+                println!("[!] Simulation Complete. Processed {} strands. Surviving: {} (in {}).", total_strands, kept_strands, output);
+            }
         }
     }
     Ok(())
 }
+
+/// Adds the CLI banner (and override hint) to pipeline errors users must act on.
+fn decorate_error(err: anyhow::Error) -> anyhow::Error {
+    if let Some(e) = err.downcast_ref::<UnstableStrands>() {
+        return anyhow::anyhow!("\n[✘] SAFETY HALT in {}: {} unstable strands after {} retries. Use --force to override.",
+                               e.scope, e.unstable, e.retries);
+    }
+    match err.downcast_ref::<PipelineError>() {
+        Some(e @ PipelineError::Decryption { .. }) => anyhow::anyhow!("\n[!] SECURITY ERROR: {}", e),
+        Some(PipelineError::CorruptBlock { block_id, codec, data_shards, parity_shards }) => anyhow::anyhow!(
+            "\n[!] CORRUPT BLOCK {}: Reconstructed header is inconsistent. Was the archive compiled with {} Config {}+{}?",
+            block_id, codec, data_shards, parity_shards),
+        None => err,
+    }
+}
+
+/// Opens every input FASTA for streaming, in the order given.
+fn open_inputs(paths: &[String]) -> Result<Vec<BufReader<File>>> {
+    paths.iter()
+    .map(|p| File::open(p).map(BufReader::new).context(format!("Failed to open DNA file: {}", p)))
+    .collect()
+}
+
+/// Droplets beyond `data` source symbols for an `--overhead` ratio.
+fn fountain_surplus(data: usize, ratio: f64) -> usize {
+    (data as f64 * ratio).ceil() as usize - data
+}
+
+/// Echoes the archive manifest recovered from the metadata strands.
+fn print_manifest(m: &Manifest) {
+    let geometry = match m.codec {
+        Codec::ReedSolomon => "RS Config",
+        Codec::Fountain => "Fountain",
+    };
+    let size = match (m.file_len, m.total_blocks) {
+        (Some(len), Some(blocks)) => format!("{} bytes, {} blocks", len, blocks),
+        _ => "streamed, length unknown".to_string(),
+    };
+    println!("[i] Manifest: '{}' ({}) | {}: {}+{} | Chunk: {} KB | {} | Encrypted: {} | Tubes: {}",
+             m.file_name, size, geometry, m.data_shards, m.parity_shards,
+             m.chunk_size / 1024, m.compression, if m.encrypted { "yes" } else { "no" }, m.tubes);
+}
+
+/// Echoes the provenance stamps found in a pool, warning if runs are mixed.
+fn print_provenance(ledger: &ProvenanceLedger) {
+    if ledger.is_empty() {
+        println!("[i] Provenance: none found (pre-provenance archive or stamps lost).");
+        return;
+    }
+    for (p, tubes) in ledger.runs() {
+        println!("[i] Archive {} | Created {} | helix v{} | Seeds {} | Tubes {:?}", p.uuid(), p.created_utc(), p.tool_version, p.seed_policy, tubes);
+    }
+    if ledger.len() > 1 {
+        println!("[!] MIXED POOL: Strands from {} different compile runs are present.", ledger.len());
+    }
+}
+
+/// Prints reads-per-strand coverage and what consensus voting contributed.
+fn print_coverage_report(c: &CoverageStats, min_coverage: u32) {
+    if c.strands == 0 { return; }
+    println!("    Strands Observed:  {} (reads per strand: min {} / median {} / mean {:.1} / max {})",
+             c.strands, c.min, c.median, c.mean, c.max);
+    println!("    Single-Read:       {}", c.single_read);
+    println!("    Consensus Calls:   {} ({} shards rescued)", c.consensus_calls, c.consensus_rescued);
+    if c.below_min_coverage > 0 {
+        println!("    Below Coverage:    {} strands with fewer than {} reads were not used", c.below_min_coverage, min_coverage);
+    }
+    if c.unassigned_reads > 0 {
+        println!("    Unassigned Reads:  {}", c.unassigned_reads);
+    }
+    println!("--------------------------------------------------");
+}
+
+/// Prints the per-strand decode breakdown gathered during restore.
+fn print_decode_report(t: &DecodeTelemetry) {
+    println!("--------------------------------------------------");
+    println!("    Strands Inspected: {}", t.total());
+    println!("    Fast Path:         {}", t.fast_path);
+    println!("    Address Healed:    {}", t.address_healed);
+    println!("    Payload Healed:    {}", t.payload_healed);
+    println!("    Indel Healed:      {}", t.indel_healed);
+    println!("    CRC Failures:      {}", t.crc_failures);
+    println!("    Address Failures:  {}", t.address_failures);
+    println!("    Primer Mismatches: {}", t.primer_mismatches);
+    println!("    Bad Shard Index:   {}", t.index_out_of_range);
+    if t.provenance + t.manifest > 0 {
+        println!("    Metadata Strands:  {}", t.provenance + t.manifest);
+    }
+    if t.malformed_headers > 0 {
+        println!("    Foreign Records:   {}", t.malformed_headers);
+    }
+    println!("--------------------------------------------------");
+}
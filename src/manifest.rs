@@ -0,0 +1,421 @@
+// src/manifest.rs
+// ARCHIVE MANIFEST
+// A small metadata record describing how the archive was built, so `restore`
+// needs nothing but the primers (the physical PCR key) to decode it.
+//
+// The record is split with its own fixed Reed-Solomon geometry (4+4) and emitted
+// as metadata strands at the head of every tube, each at a reserved address:
+//   Strand: [FP] [Address = MANIFEST_INDEX_BASE + i] [CRC32 + RS Shard i] [RP]
+//
+// Record: [Magic "HXMF" 4] [Ver 1] [BodyLen u16] [Body...] [CRC32 of everything before]
+// Body:   [UUID 16] [Data u16] [Parity u16] [Chunk u32] [Compression 1] [Level 1]
+//         [Encrypted 1] [FileLen u64] [Blocks u32] [Tubes u16] [Salt 1] [Codec 1] [NameLen u16] [Name...]
+// FileLen and Blocks are all ones when the archive was compiled from a stream of unknown length.
+// Version 1 records (no Codec byte) are still read, as Reed-Solomon.
+//
+// Every tube carries a byte-identical manifest, so shards from different tubes mix freely.
+// Like provenance strands, manifest strands always use chained seeds.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use crate::dna_mapper::{DnaMapper, UnstableStrands};
+use crate::oligo::Oligo;
+use crate::rs_engine::RedundancyManager;
+
+/// FASTA header (without `>`) used by manifest strands.
+pub const MANIFEST_HEADER: &str = "helix_manifest";
+
+/// First reserved address index; shard `i` sits at `MANIFEST_INDEX_BASE + i`.
+pub const MANIFEST_INDEX_BASE: u32 = 0xFFFF_FF00;
+
+/// Fixed RS geometry of the manifest itself (survives the loss of any 4 of 8 strands).
+pub const MANIFEST_DATA_SHARDS: usize = 4;
+pub const MANIFEST_PARITY_SHARDS: usize = 4;
+pub const MANIFEST_TOTAL_SHARDS: usize = MANIFEST_DATA_SHARDS + MANIFEST_PARITY_SHARDS;
+
+const MAGIC: &[u8; 4] = b"HXMF";
+const RECORD_VERSION: u8 = 2;
+const FIXED_BODY_LEN: usize = 16 + 2 + 2 + 4 + 1 + 1 + 1 + 8 + 4 + 2 + 1 + 1 + 2;
+const MAX_STABILITY_RETRIES: u8 = 32;
+
+/// Distinct copies kept per shard index (a pool rarely mixes more than a few archives).
+const MAX_SHARD_COPIES: usize = 4;
+
+/// Block payload compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Zstd { level: u8 },
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Zstd { level } => write!(f, "zstd (level {})", level),
+        }
+    }
+}
+
+impl Compression {
+    fn to_bytes(self) -> [u8; 2] {
+        match self {
+            Compression::None => [0, 0],
+            Compression::Zstd { level } => [1, level],
+        }
+    }
+
+    fn from_bytes(code: u8, level: u8) -> Option<Self> {
+        match code {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Zstd { level }),
+            _ => None,
+        }
+    }
+}
+
+/// Block erasure code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Codec {
+    /// Fixed N data + K parity shards (`rs_engine`).
+    #[default]
+    #[serde(rename = "rs")]
+    ReedSolomon,
+    /// Rateless LT droplets (`fountain`): N source symbols, K surplus droplets.
+    #[serde(rename = "fountain")]
+    Fountain,
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Codec::ReedSolomon => write!(f, "rs"),
+            Codec::Fountain => write!(f, "fountain"),
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = String;
+
+    /// Accepts `rs` (or `reed-solomon`) and `fountain` (or `lt`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rs" | "reed-solomon" => Ok(Codec::ReedSolomon),
+            "fountain" | "lt" => Ok(Codec::Fountain),
+            other => Err(format!("unknown codec '{}' (expected 'rs' or 'fountain')", other)),
+        }
+    }
+}
+
+impl Codec {
+    fn from_byte(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Codec::ReedSolomon),
+            1 => Some(Codec::Fountain),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    /// UUID of the compile run (matches the provenance stamp).
+    pub archive_id: [u8; 16],
+    pub codec: Codec,
+    /// Reed-Solomon: N data + K parity shards. Fountain: N source symbols + K surplus droplets.
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    /// Plaintext bytes per block (`STREAMING_CHUNK_SIZE` at compile time).
+    pub chunk_size: u32,
+    pub compression: Compression,
+    /// Blocks are Argon2id/HKDF/AES-256-GCM encrypted.
+    pub encrypted: bool,
+    /// Original file name (no directories).
+    pub file_name: String,
+    /// Plaintext length; `None` if compiled from a stream of unknown length.
+    pub file_len: Option<u64>,
+    /// Total data blocks across all tubes (`None` alongside an unknown length).
+    pub total_blocks: Option<u32>,
+    pub tubes: u16,
+    /// Re-rolled until every manifest strand passes the stability check.
+    pub salt: u8,
+}
+
+impl Manifest {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let name = self.file_name.as_bytes();
+        let name = &name[..name.len().min(u16::MAX as usize - FIXED_BODY_LEN)];
+
+        let mut body = Vec::with_capacity(FIXED_BODY_LEN + name.len());
+        body.extend_from_slice(&self.archive_id);
+        body.extend_from_slice(&(self.data_shards as u16).to_be_bytes());
+        body.extend_from_slice(&(self.parity_shards as u16).to_be_bytes());
+        body.extend_from_slice(&self.chunk_size.to_be_bytes());
+        body.extend_from_slice(&self.compression.to_bytes());
+        body.push(self.encrypted as u8);
+        body.extend_from_slice(&self.file_len.unwrap_or(u64::MAX).to_be_bytes());
+        body.extend_from_slice(&self.total_blocks.unwrap_or(u32::MAX).to_be_bytes());
+        body.extend_from_slice(&self.tubes.to_be_bytes());
+        body.push(self.salt);
+        body.push(self.codec as u8);
+        body.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        body.extend_from_slice(name);
+
+        let mut out = Vec::with_capacity(body.len() + 11);
+        out.extend_from_slice(MAGIC);
+        out.push(RECORD_VERSION);
+        out.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        out.extend_from_slice(&body);
+        out.extend_from_slice(&crc32fast::hash(&out).to_be_bytes());
+        out
+    }
+
+    /// Parses a record; trailing bytes (RS zero-padding) are ignored.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 7 || &bytes[0..4] != MAGIC || !(1..=RECORD_VERSION).contains(&bytes[4]) {
+            return None;
+        }
+        // Version 1 has no Codec byte.
+        let fixed_len = if bytes[4] == 1 { FIXED_BODY_LEN - 1 } else { FIXED_BODY_LEN };
+        let body_len = u16::from_be_bytes(bytes[5..7].try_into().ok()?) as usize;
+        let crc_at = 7 + body_len;
+        let crc = u32::from_be_bytes(bytes.get(crc_at..crc_at + 4)?.try_into().ok()?);
+        if body_len < fixed_len || crc32fast::hash(&bytes[..crc_at]) != crc {
+            return None;
+        }
+
+        let b = &bytes[7..crc_at];
+        let u16_at = |i: usize| u16::from_be_bytes([b[i], b[i + 1]]);
+        let codec = if bytes[4] == 1 { Codec::ReedSolomon } else { Codec::from_byte(b[42])? };
+        let name_len = u16_at(fixed_len - 2) as usize;
+        let file_name = String::from_utf8(b.get(fixed_len..fixed_len + name_len)?.to_vec()).ok()?;
+
+        Some(Self {
+            archive_id: b[0..16].try_into().ok()?,
+            codec,
+            data_shards: u16_at(16) as usize,
+            parity_shards: u16_at(18) as usize,
+            chunk_size: u32::from_be_bytes(b[20..24].try_into().ok()?),
+            compression: Compression::from_bytes(b[24], b[25])?,
+            encrypted: b[26] != 0,
+            file_len: Some(u64::from_be_bytes(b[27..35].try_into().ok()?)).filter(|&l| l != u64::MAX),
+            total_blocks: Some(u32::from_be_bytes(b[35..39].try_into().ok()?)).filter(|&b| b != u32::MAX),
+            tubes: u16_at(39),
+            salt: b[41],
+            file_name,
+        })
+    }
+
+    /// RS-encodes the record and builds its FASTA records for one primer pair.
+    pub fn to_fasta_entries(&self, primers: (&str, &str)) -> Result<Vec<String>> {
+        let rs = RedundancyManager::new(MANIFEST_DATA_SHARDS, MANIFEST_PARITY_SHARDS)?;
+        let shards = rs.encode_to_shards(&self.to_bytes())?;
+
+        Ok(shards.iter().enumerate().map(|(i, shard)| {
+            let mut protected = crc32fast::hash(shard).to_be_bytes().to_vec();
+            protected.extend_from_slice(shard);
+            let strand = Oligo::create_tagged(MANIFEST_INDEX_BASE + i as u32, &protected, primers);
+            format!(">{}\n{}\n", MANIFEST_HEADER, strand)
+        }).collect())
+    }
+
+    /// Re-rolls `salt` until every strand is stable under every primer pair.
+    /// Fails if no salt qualifies, unless `force` accepts the last one.
+    pub fn stabilize(mut self, primer_sets: &[(String, String)], force: bool) -> Result<Self> {
+        let mut unstable = 0;
+        for salt in 0..MAX_STABILITY_RETRIES {
+            self.salt = salt;
+            unstable = 0;
+            for (fp, rp) in primer_sets {
+                unstable += self.to_fasta_entries((fp, rp))?.iter().filter(|entry| {
+                    let strand = entry.lines().nth(1).unwrap_or("");
+                    !DnaMapper::analyze_stability(strand).is_stable
+                }).count();
+            }
+            if unstable == 0 { return Ok(self); }
+        }
+        if !force {
+            let scope = "Manifest".to_string();
+            return Err(UnstableStrands { scope, unstable, retries: MAX_STABILITY_RETRIES as usize }.into());
+        }
+        Ok(self)
+    }
+}
+
+/// Gathers manifest shards from a read stream until the record can be rebuilt.
+#[derive(Debug, Default)]
+pub struct ManifestCollector {
+    /// Distinct copies seen at each shard index, oldest first.
+    shards: HashMap<usize, Vec<Vec<u8>>>,
+    manifest: Option<Manifest>,
+}
+
+impl ManifestCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds one verified shard; returns the manifest once it is recoverable.
+    /// A pool may mix archives under the same primers, so every distinct copy of an
+    /// index is kept and each new one is tried against the others. A reconstruction
+    /// that fails the record CRC is rejected, never guessed.
+    pub fn insert(&mut self, index: usize, data: Vec<u8>) -> Result<Option<&Manifest>> {
+        if self.manifest.is_none() && index < MANIFEST_TOTAL_SHARDS {
+            let copies = self.shards.entry(index).or_default();
+            if copies.len() < MAX_SHARD_COPIES && !copies.contains(&data) {
+                copies.push(data);
+                self.manifest = self.rebuild(index)?;
+            }
+        }
+        Ok(self.manifest.as_ref())
+    }
+
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    pub fn into_manifest(self) -> Option<Manifest> {
+        self.manifest
+    }
+
+    /// Tries every set of `MANIFEST_DATA_SHARDS` indices that includes the newest copy
+    /// at `index`, with every combination of the other indices' copies.
+    fn rebuild(&self, index: usize) -> Result<Option<Manifest>> {
+        let rs = RedundancyManager::new(MANIFEST_DATA_SHARDS, MANIFEST_PARITY_SHARDS)?;
+        let newest = self.shards[&index].last().cloned();
+        let others: Vec<usize> = (0..MANIFEST_TOTAL_SHARDS).filter(|i| *i != index && self.shards.contains_key(i)).collect();
+
+        for subset in combinations(others.len(), MANIFEST_DATA_SHARDS - 1) {
+            let copies: Vec<&Vec<Vec<u8>>> = subset.iter().map(|&s| &self.shards[&others[s]]).collect();
+            let mut pick = vec![0; subset.len()];
+            loop {
+                let mut slots = vec![None; MANIFEST_TOTAL_SHARDS];
+                slots[index] = newest.clone();
+                for (k, &s) in subset.iter().enumerate() {
+                    slots[others[s]] = Some(copies[k][pick[k]].clone());
+                }
+                if let Some(manifest) = rs.recover_file(slots).ok().and_then(|record| Manifest::from_bytes(&record)) {
+                    return Ok(Some(manifest));
+                }
+                // Next combination of copies (odometer over `pick`).
+                let Some(k) = (0..pick.len()).find(|&k| pick[k] + 1 < copies[k].len()) else { break };
+                pick[k] += 1;
+                pick[..k].iter_mut().for_each(|p| *p = 0);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// All `k`-element index subsets of `0..n`, in lexicographic order.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k > n { return Vec::new(); }
+    let mut out = Vec::new();
+    let mut set: Vec<usize> = (0..k).collect();
+    loop {
+        out.push(set.clone());
+        let Some(i) = (0..k).rev().find(|&i| set[i] < n - k + i) else { return out };
+        set[i] += 1;
+        for j in i + 1..k {
+            set[j] = set[j - 1] + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(codec: Codec) -> Manifest {
+        Manifest {
+            archive_id: [7; 16],
+            codec,
+            data_shards: 100,
+            parity_shards: 52,
+            chunk_size: 4 * 1024 * 1024,
+            compression: Compression::Zstd { level: 3 },
+            encrypted: true,
+            file_name: "photos.tar".into(),
+            file_len: Some(9_000_000),
+            total_blocks: Some(3),
+            tubes: 2,
+            salt: 5,
+        }
+    }
+
+    /// Re-frames a current record as version 1: no Codec byte, fresh length and CRC.
+    fn downgrade_to_v1(record: &[u8]) -> Vec<u8> {
+        let mut body = record[7..record.len() - 4].to_vec();
+        body.remove(FIXED_BODY_LEN - 3);
+        let mut out = MAGIC.to_vec();
+        out.push(1);
+        out.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        out.extend_from_slice(&body);
+        out.extend_from_slice(&crc32fast::hash(&out).to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn record_round_trips() {
+        for m in [manifest(Codec::ReedSolomon), manifest(Codec::Fountain)] {
+            let mut bytes = m.to_bytes();
+            bytes.extend_from_slice(&[0; 9]);
+            assert_eq!(Manifest::from_bytes(&bytes), Some(m));
+        }
+    }
+
+    #[test]
+    fn v1_record_decodes_as_reed_solomon() {
+        let v1 = downgrade_to_v1(&manifest(Codec::Fountain).to_bytes());
+        assert_eq!(Manifest::from_bytes(&v1), Some(manifest(Codec::ReedSolomon)));
+    }
+
+    #[test]
+    fn damaged_records_are_rejected() {
+        let bytes = manifest(Codec::ReedSolomon).to_bytes();
+        let mut flipped = bytes.clone();
+        flipped[20] ^= 1;
+        assert_eq!(Manifest::from_bytes(&flipped), None);
+        assert_eq!(Manifest::from_bytes(&bytes[..bytes.len() - 1]), None);
+
+        let mut future = bytes.clone();
+        future[4] = RECORD_VERSION + 1;
+        assert_eq!(Manifest::from_bytes(&future), None);
+    }
+
+    #[test]
+    fn foreign_shard_does_not_block_the_rebuild() {
+        let rs = RedundancyManager::new(MANIFEST_DATA_SHARDS, MANIFEST_PARITY_SHARDS).unwrap();
+        let m = manifest(Codec::ReedSolomon);
+        let foreign = Manifest { archive_id: [9; 16], file_name: "other.bin".into(), ..m.clone() };
+        let shards = rs.encode_to_shards(&m.to_bytes()).unwrap();
+        let foreign_shards = rs.encode_to_shards(&foreign.to_bytes()).unwrap();
+
+        let mut collector = ManifestCollector::new();
+        assert_eq!(collector.insert(0, foreign_shards[0].clone()).unwrap(), None);
+        for (i, shard) in shards.iter().enumerate() {
+            if let Some(rebuilt) = collector.insert(i, shard.clone()).unwrap() {
+                assert_eq!(rebuilt, &m);
+                return;
+            }
+        }
+        panic!("manifest was not rebuilt from the good shards");
+    }
+
+    #[test]
+    fn collector_rebuilds_from_any_data_shard_count() {
+        let m = manifest(Codec::Fountain);
+        let rs = RedundancyManager::new(MANIFEST_DATA_SHARDS, MANIFEST_PARITY_SHARDS).unwrap();
+        let shards = rs.encode_to_shards(&m.to_bytes()).unwrap();
+
+        let mut collector = ManifestCollector::new();
+        for i in (0..MANIFEST_TOTAL_SHARDS).rev().take(MANIFEST_DATA_SHARDS - 1) {
+            assert_eq!(collector.insert(i, shards[i].clone()).unwrap(), None);
+        }
+        assert_eq!(collector.insert(0, shards[0].clone()).unwrap(), Some(&m));
+    }
+}
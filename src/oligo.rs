@@ -7,7 +7,12 @@
 // - Address: 24bp Base-3 sequence containing Block ID and Shard Index.
 // - Payload: Variable length Base-3 encoded data.
 
-use crate::dna_mapper::{DnaMapper, Base};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use anyhow::{Result, anyhow, bail};
+use sha2::{Sha256, Digest};
+use crate::dna_mapper::{DnaMapper, Base, MAX_INDEL_SHIFT};
 
 // Defaults using high-entropy sequences (balanced GC, no homopolymers)
 pub const DEFAULT_FP: &str = "GCTACGATCGTAGCTAGCTA";
@@ -16,8 +21,108 @@ pub const DEFAULT_RP: &str = "CGATCGTAGCTAGCTAGCTA";
 // 4 bytes for index * 6 trits/byte = 24 bases
 pub const ADDRESS_BASE_LEN: usize = 24;
 
+// Max mismatches tolerated per primer by the fuzzy gatekeeper during restore.
+pub const PRIMER_TOLERANCE: usize = 3;
+
+// Min Hamming distance between any two primers of an orthogonal set.
+// Must exceed 2 * PRIMER_TOLERANCE so a damaged primer can never be
+// within tolerance of two different tubes at once.
+pub const MIN_PRIMER_DISTANCE: usize = 2 * PRIMER_TOLERANCE + 1;
+
+// Candidates tried per tube before orthogonal primer derivation gives up.
+const MAX_PRIMER_ATTEMPTS: u32 = 10_000;
+
 pub struct Oligo;
 
+/// How the trellis seeds (start bases) of the Address and Payload regions are chosen.
+///
+/// The seed is the "previous base" the decoder must assume before the first symbol of a
+/// region; getting it wrong shifts every trit. It is therefore an explicit, recorded
+/// archive parameter (provenance strand + tube map) rather than something decode infers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SeedPolicy {
+    /// HES-1 default: Address seeds off the last base of the Forward Primer,
+    /// Payload off the last base of the Address. Guarantees seamless boundaries.
+    #[default]
+    Chained,
+    /// Fixed seeds, independent of primers. Seams may form a homopolymer,
+    /// but decoding no longer depends on how the primers end.
+    Fixed { address: Base, payload: Base },
+}
+
+impl SeedPolicy {
+    /// Seed for the Address region.
+    pub fn address_seed(&self, fp: &str) -> Option<Base> {
+        match self {
+            SeedPolicy::Chained => fp.chars().last().and_then(Base::from_char),
+            SeedPolicy::Fixed { address, .. } => Some(*address),
+        }
+    }
+
+    /// Seed for the Payload region, given the (corrected) Address DNA.
+    pub fn payload_seed(&self, address_dna: &str) -> Option<Base> {
+        match self {
+            SeedPolicy::Chained => address_dna.chars().last().and_then(Base::from_char),
+            SeedPolicy::Fixed { payload, .. } => Some(*payload),
+        }
+    }
+
+    /// Compact binary form for archive metadata: `[0]` or `[1, addr, payload]`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SeedPolicy::Chained => vec![0],
+            SeedPolicy::Fixed { address, payload } => vec![1, address.idx() as u8, payload.idx() as u8],
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0, ..] => Some(SeedPolicy::Chained),
+            [1, a, p, ..] => Some(SeedPolicy::Fixed {
+                address: *Base::all().get(*a as usize)?,
+                payload: *Base::all().get(*p as usize)?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SeedPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeedPolicy::Chained => write!(f, "chained"),
+            SeedPolicy::Fixed { address, payload } => write!(f, "fixed:{}{}", address.to_char(), payload.to_char()),
+        }
+    }
+}
+
+impl FromStr for SeedPolicy {
+    type Err = String;
+
+    /// Accepts `chained` or `fixed:XY` (X = Address seed, Y = Payload seed).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.trim().to_ascii_uppercase();
+        if upper == "CHAINED" {
+            return Ok(SeedPolicy::Chained);
+        }
+
+        let mut seeds = upper.strip_prefix("FIXED:").unwrap_or("").chars().map(Base::from_char);
+        match (seeds.next().flatten(), seeds.next().flatten(), seeds.next()) {
+            (Some(address), Some(payload), None) => Ok(SeedPolicy::Fixed { address, payload }),
+            _ => Err(format!("invalid seed policy '{}': expected 'chained' or 'fixed:XY' (X, Y in ACGT)", s)),
+        }
+    }
+}
+
+/// Byte offsets of each physical region within an assembled strand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrandRegions {
+    pub fwd_primer: Range<usize>,
+    pub address: Range<usize>,
+    pub payload: Range<usize>,
+    pub rev_primer: Range<usize>,
+}
+
 impl Oligo {
     /// Generates deterministic primers from a user-provided string tag.
     /// This allows "Molecular Addressing" - extracting specific files from a pool.
@@ -65,28 +170,112 @@ impl Oligo {
         (fp, rp)
     }
 
-    /// Assembles a full DNA strand with "Trellis Chaining".
+    /// Derives `count` mutually orthogonal primer pairs for multi-tube archives.
+    /// Tube 0 always uses `base`; further pairs are drawn deterministically from
+    /// SHA-256(base, tube, attempt) so restore can regenerate the same set.
+    /// Candidates must be GC balanced and at least MIN_PRIMER_DISTANCE from every
+    /// primer already chosen (forward and reverse alike). Fails if a pair cannot be
+    /// found within MAX_PRIMER_ATTEMPTS candidates.
+    pub fn orthogonal_primer_set(base: (&str, &str), count: usize) -> Result<Vec<(String, String)>> {
+        let (base_fp, base_rp) = base;
+        let mut set = vec![(base_fp.to_string(), base_rp.to_string())];
+        let mut attempt = 0u32;
+        let mut rejected = 0u32;
+
+        let hamming = |a: &str, b: &str| -> usize {
+            a.chars().zip(b.chars()).filter(|(c1, c2)| c1 != c2).count() + a.len().abs_diff(b.len())
+        };
+        let gc_ok = |p: &str| {
+            let gc = DnaMapper::analyze_stability(p).gc_content;
+            (40.0..=60.0).contains(&gc)
+        };
+
+        while set.len() < count {
+            if rejected == MAX_PRIMER_ATTEMPTS {
+                bail!("Could not derive {} orthogonal primer pairs: no candidate for pair {} in {} attempts.",
+                      count, set.len() + 1, MAX_PRIMER_ATTEMPTS);
+            }
+            rejected += 1;
+
+            let mut hasher = Sha256::new();
+            hasher.update(base_fp.as_bytes());
+            hasher.update(base_rp.as_bytes());
+            hasher.update((set.len() as u32).to_be_bytes());
+            hasher.update(attempt.to_be_bytes());
+            attempt = attempt.checked_add(1).ok_or_else(|| anyhow!("Primer derivation exhausted its seed space at {} pairs.", set.len()))?;
+
+            // 32 digest bytes -> 192 homopolymer-free bases; take two 20bp windows.
+            let dna = DnaMapper::encode_shard(&hasher.finalize(), Base::A);
+            let (fp, rp) = (&dna[..20], &dna[20..40]);
+
+            if !gc_ok(fp) || !gc_ok(rp) || hamming(fp, rp) < MIN_PRIMER_DISTANCE { continue; }
+
+            let orthogonal = set.iter().all(|(f, r)| {
+                [f.as_str(), r.as_str()].iter().all(|p| hamming(fp, p) >= MIN_PRIMER_DISTANCE && hamming(rp, p) >= MIN_PRIMER_DISTANCE)
+            });
+            if orthogonal {
+                set.push((fp.to_string(), rp.to_string()));
+                rejected = 0;
+            }
+        }
+        Ok(set)
+    }
+
+    /// Checks that a primer pair is usable: non-empty and strictly ACGT.
+    /// Anything else would silently corrupt the trellis seed or the primer gate.
+    pub fn validate_primers(primers: (&str, &str)) -> Result<()> {
+        for (label, p) in [("Forward", primers.0), ("Reverse", primers.1)] {
+            if p.is_empty() {
+                bail!("{} primer is empty.", label);
+            }
+            if let Some(bad) = p.chars().find(|c| Base::from_char(*c).is_none()) {
+                bail!("{} primer '{}' contains invalid base '{}' (only uppercase A/C/G/T allowed).", label, p, bad);
+            }
+        }
+        Ok(())
+    }
+
+    /// Assembles a full DNA strand with "Trellis Chaining" (HES-1 default seeds).
     /// The start base of the Address depends on the FP.
     /// The start base of the Payload depends on the Address.
     /// This ensures the No-Homopolymer rule is never broken at boundaries.
     pub fn create_tagged(index: u32, payload_bytes: &[u8], primers: (&str, &str)) -> String {
+        Self::create_tagged_with(index, payload_bytes, primers, SeedPolicy::Chained)
+    }
+
+    /// Assembles a full DNA strand using an explicit seed policy.
+    pub fn create_tagged_with(index: u32, payload_bytes: &[u8], primers: (&str, &str), seeds: SeedPolicy) -> String {
         let (fp, rp) = primers;
         let index_bytes = index.to_be_bytes();
 
-        // 1. Chain Address to Forward Primer
-        let last_char_fp = fp.chars().last().unwrap_or('A');
-        let start_base_addr = Base::from_char(last_char_fp).unwrap_or(Base::A);
+        // 1. Seed Address (Chained: from Forward Primer tail)
+        let start_base_addr = seeds.address_seed(fp).unwrap_or(Base::A);
         let address_dna = DnaMapper::encode_shard(&index_bytes, start_base_addr);
 
-        // 2. Chain Payload to Address
-        let last_char_addr = address_dna.chars().last().unwrap_or('A');
-        let start_base_payload = Base::from_char(last_char_addr).unwrap_or(Base::A);
+        // 2. Seed Payload (Chained: from Address tail)
+        let start_base_payload = seeds.payload_seed(&address_dna).unwrap_or(Base::A);
         let payload_dna = DnaMapper::encode_shard(payload_bytes, start_base_payload);
 
         // 3. Assemble
         format!("{}{}{}{}", fp, address_dna, payload_dna, rp)
     }
 
+    /// Computes the region layout of a strand of `strand_len` bases built by `create_tagged`.
+    /// Regions are clamped so a truncated strand never yields out-of-bounds ranges.
+    pub fn regions(strand_len: usize, primers: (&str, &str)) -> StrandRegions {
+        let (fp, rp) = primers;
+        let addr_start = fp.len().min(strand_len);
+        let addr_end = (addr_start + ADDRESS_BASE_LEN).min(strand_len);
+        let rp_start = strand_len.saturating_sub(rp.len()).max(addr_end);
+
+        StrandRegions {
+            fwd_primer: 0..addr_start,
+            address: addr_start..addr_end,
+            payload: addr_end..rp_start,
+            rev_primer: rp_start..strand_len,
+        }
+    }
+
     /// STRICT STRIP: Exact match only (Fast).
     /// Used when high throughput is prioritized over recovery.
     pub fn strip_tagged_exact<'a>(strand: &'a str, primers: (&str, &str)) -> Option<&'a str> {
@@ -118,4 +307,44 @@ impl Oligo {
 
         None
     }
+
+    /// INDEL STRIP: Like `strip_tagged_fuzzy`, but scores each primer by edit distance over
+    /// windows up to `MAX_INDEL_SHIFT` bases shorter or longer, so a primer with an
+    /// insertion or deletion still releases the core. Only used after the Hamming gate
+    /// fails: edit distance is looser than the Hamming spacing of orthogonal primer sets.
+    pub fn strip_tagged_indel<'a>(strand: &'a str, primers: (&str, &str), max_err: usize) -> Option<&'a str> {
+        let (fp, rp) = primers;
+        if !strand.is_ascii() { return None; }
+
+        // Best window length for a primer at one end: lowest edit distance,
+        // ties broken towards the nominal length.
+        let best_window = |primer: &str, window: &dyn Fn(usize) -> Option<&'a str>| -> Option<usize> {
+            let lo = primer.len().saturating_sub(MAX_INDEL_SHIFT);
+            (lo..=primer.len() + MAX_INDEL_SHIFT)
+            .filter_map(|len| Some((len, edit_distance(window(len)?, primer))))
+            .filter(|&(_, dist)| dist <= max_err)
+            .min_by_key(|&(len, dist)| (dist, len.abs_diff(primer.len())))
+            .map(|(len, _)| len)
+        };
+
+        let fp_len = best_window(fp, &|len| strand.get(..len))?;
+        let rp_len = best_window(rp, &|len| strand.len().checked_sub(len).and_then(|start| strand.get(start..)))?;
+        strand.get(fp_len..strand.len().checked_sub(rp_len)?)
+    }
+}
+
+/// Levenshtein distance (substitution, insertion, deletion all cost 1).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.bytes().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let next = (diag + (ca != cb) as usize).min(row[j] + 1).min(row[j + 1] + 1);
+            diag = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
 }
@@ -2,12 +2,23 @@
 // PARALLEL PROCESSING ENGINE
 // Handles the heavy lifting of DNA encoding/decoding using Rayon.
 // Implements the Multi-Stage Viterbi Recovery pipeline.
+// Without the `parallel` feature the same code runs on plain iterators.
 
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
+#[cfg(not(feature = "parallel"))]
+use crate::sequential::*;
 use crc32fast::Hasher;
-use rand::{seq::SliceRandom, thread_rng, Rng};
-use crate::dna_mapper::{DnaMapper, StabilityReport, Base};
-use crate::oligo::{Oligo, ADDRESS_BASE_LEN};
+use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use crate::dna_mapper::{Base, DnaMapper, StabilityReport, MAX_INDEL_SHIFT};
+use std::collections::HashMap;
+use crate::oligo::{Oligo, SeedPolicy, StrandRegions, ADDRESS_BASE_LEN, PRIMER_TOLERANCE};
+use crate::telemetry::DecodeOutcome;
+use crate::error_profile::ErrorProfile;
+use crate::provenance::{Provenance, PROVENANCE_INDEX};
+use crate::manifest::{MANIFEST_INDEX_BASE, MANIFEST_TOTAL_SHARDS};
+use crate::fasta_header::{self, StrandHeader};
+use crate::consensus;
 
 pub struct ParallelProcessor;
 
@@ -15,7 +26,43 @@ pub struct ParallelProcessor;
 pub struct ShardResult {
     pub index: usize,
     pub fasta_entry: String,
+    pub strand_len: usize,
+    pub regions: StrandRegions,
     pub stability: StabilityReport,
+    pub region_stability: RegionStability,
+}
+
+/// Stability metrics for each physical region of a strand.
+/// Short regions (primers, address) naturally report a low Tm; the GC breakdown
+/// is the useful signal there, e.g. spotting a primer that skews the whole strand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionStability {
+    pub fwd_primer: StabilityReport,
+    pub address: StabilityReport,
+    pub payload: StabilityReport,
+    pub rev_primer: StabilityReport,
+}
+
+/// All shards of a processed block plus block-level diagnostics.
+pub struct BlockResult {
+    pub shards: Vec<ShardResult>,
+    /// Pairs of shard indices whose shard bytes are identical.
+    /// Chained seeds encode equal shards to different payload DNA, so the bytes are
+    /// compared rather than the strands; a repeat indicates degenerate input or a
+    /// whitening (encryption) failure.
+    pub collisions: Vec<(usize, usize)>,
+}
+
+impl ShardResult {
+    /// The bare DNA strand (FASTA entry without header).
+    pub fn strand(&self) -> &str {
+        self.fasta_entry.lines().nth(1).unwrap_or("")
+    }
+
+    /// The payload region of the strand.
+    pub fn payload(&self) -> &str {
+        &self.strand()[self.regions.payload.clone()]
+    }
 }
 
 impl ParallelProcessor {
@@ -24,12 +71,27 @@ impl ParallelProcessor {
     /// 2. Encodes to DNA (Trellis).
     /// 3. Attaches Primers.
     /// 4. Checks Biological Stability.
+    /// 5. Flags shards with identical bytes.
+    ///
+    /// `header` is the block's header template; shard index and CRC are filled per shard.
     pub fn process_block(
-        block_id: u32,
+        header: &StrandHeader,
         shards: Vec<Vec<u8>>,
-        primers: (&str, &str)
-    ) -> Vec<ShardResult> {
-        shards.into_par_iter()
+        primers: (&str, &str),
+        seeds: SeedPolicy
+    ) -> BlockResult {
+        // 0. Collision Detection (before the shards are consumed)
+        let mut seen: HashMap<&[u8], usize> = HashMap::with_capacity(shards.len());
+        let mut collisions = Vec::new();
+        for (i, shard) in shards.iter().enumerate() {
+            if let Some(&first) = seen.get(shard.as_slice()) {
+                collisions.push((first, i));
+            } else {
+                seen.insert(shard, i);
+            }
+        }
+
+        let results: Vec<ShardResult> = shards.into_par_iter()
         .enumerate()
         .map(|(i, shard)| {
             // 1. Integrity (CRC32)
@@ -42,40 +104,84 @@ impl ParallelProcessor {
             protected_shard.extend_from_slice(&shard);
 
             // 2. Transcoding & Packaging
-            let header = format!(">blk{}_s{}\n", block_id, i);
-            let finalized = Oligo::create_tagged(i as u32, &protected_shard, primers);
+            let header = StrandHeader { shard: i, crc: Some(crc), ..header.clone() };
+            let finalized = Oligo::create_tagged_with(i as u32, &protected_shard, primers, seeds);
 
-            // 3. Stability Analysis (GC% and Tm)
+            // 3. Stability Analysis (GC% and Tm), whole strand and per region
             let stability = DnaMapper::analyze_stability(&finalized);
+            let regions = Oligo::regions(finalized.len(), primers);
+            let region_stability = RegionStability {
+                fwd_primer: DnaMapper::analyze_stability(&finalized[regions.fwd_primer.clone()]),
+                address: DnaMapper::analyze_stability(&finalized[regions.address.clone()]),
+                payload: DnaMapper::analyze_stability(&finalized[regions.payload.clone()]),
+                rev_primer: DnaMapper::analyze_stability(&finalized[regions.rev_primer.clone()]),
+            };
 
             ShardResult {
                 index: i,
-                fasta_entry: format!("{}{}\n", header, finalized),
-             stability,
+                strand_len: finalized.len(),
+                fasta_entry: format!("{}\n{}\n", header.to_line(), finalized),
+                regions,
+                stability,
+                region_stability,
             }
         })
-        .collect()
+        .collect();
+
+        BlockResult { shards: results, collisions }
+    }
+
+    /// REPLICATE: Physical copy redundancy.
+    /// Emits every FASTA entry of a block `copies` times in shuffled order, modelling the
+    /// many molecules synthesis produces per design. Shuffling stays within the block so
+    /// compile memory remains bounded. `copies == 1` preserves the original order.
+    pub fn replicate_block(shards: &[ShardResult], copies: usize) -> Vec<&str> {
+        let mut entries: Vec<&str> = Vec::with_capacity(shards.len() * copies);
+        for _ in 0..copies {
+            entries.extend(shards.iter().map(|res| res.fasta_entry.as_str()));
+        }
+        if copies > 1 {
+            entries.shuffle(&mut thread_rng());
+        }
+        entries
     }
 
     /// RESTORE: Decodes a single strand with Viterbi Error Correction.
+    /// Thin wrapper over `decode_strand` (default chained seeds) for callers that only need the shard.
+    pub fn parse_strand(
+        header: &str,
+        dna: &str,
+        primers: (&str, &str)
+    ) -> Option<(u32, usize, Vec<u8>)> {
+        Self::decode_strand(header, dna, primers, SeedPolicy::Chained).into_shard()
+    }
+
+    /// RESTORE (Instrumented): Decodes a single strand and reports its fate.
     /// PIPELINE:
-    /// 1. Fuzzy Primer Strip (Gatekeeper)
+    /// 1. Fuzzy Primer Strip (Gatekeeper, Hamming -> Edit Distance Fallback)
     /// 2. Address Decode (Standard -> Viterbi Fallback)
     /// 3. Payload Decode (Standard -> Viterbi Fallback)
     /// 4. CRC Verification
-    pub fn parse_strand(
+    /// 5. Indel Recovery (Edit-Distance Viterbi over the whole core, last resort)
+    pub fn decode_strand(
         header: &str,
         dna: &str,
-        primers: (&str, &str)
-    ) -> Option<(u32, usize, Vec<u8>)> {
+        primers: (&str, &str),
+        seeds: SeedPolicy
+    ) -> DecodeOutcome {
         // 1. Parse Header Text (Backup ID if DNA is unreadable)
-        let clean_header = header.trim_start_matches('>');
-        if !clean_header.starts_with("blk") { return None; }
-
-        let parts: Vec<&str> = clean_header.split('_').collect();
-        if parts.len() < 2 { return None; }
+        let is_provenance = fasta_header::is_provenance(header);
+        let is_manifest = fasta_header::is_manifest(header);
+        let parsed = StrandHeader::parse(header);
+        let block_id = match &parsed {
+            Some(h) => h.block_id,
+            None if is_provenance || is_manifest => 0,
+            None => return DecodeOutcome::MalformedHeader,
+        };
 
-        let block_id: u32 = parts[0].strip_prefix("blk")?.parse().ok()?;
+        // Metadata strands always use the default policy so they can be read
+        // before the archive's own policy is known.
+        let seeds = if is_provenance || is_manifest { SeedPolicy::Chained } else { seeds };
 
         // 2. Strip Primers (FUZZY MODE)
         let (fp, _) = primers;
@@ -83,42 +189,28 @@ impl ParallelProcessor {
         // Critical Fix: Use Fuzzy Matching.
         // Allow up to 3 errors in the 20bp primers (~15% tolerance).
         // This ensures the strand reaches Viterbi even if the "Zip Code" is slightly damaged.
-        let core = Oligo::strip_tagged_fuzzy(dna, primers, 3)?;
-
-        if core.len() < ADDRESS_BASE_LEN { return None; }
-
-        let address_raw = &core[..ADDRESS_BASE_LEN];
-        let payload_raw = &core[ADDRESS_BASE_LEN..];
-
-        // 3. Resolve Address Chain Start (Based on Forward Primer tail)
-        let last_fp_char = fp.chars().last().unwrap_or('A');
-        let start_base_addr = Base::from_char(last_fp_char)?;
-
-        // 4. Decode Address (With Viterbi Fallback)
-        // We need the address to be valid to get the Index AND the start seed for payload.
-        let (index, corrected_address_str) = match DnaMapper::decode_shard(address_raw, start_base_addr) {
-            Some(bytes) => {
-                // Fast Path: Address is clean
-                if bytes.len() < 4 { return None; }
-                let idx = u32::from_be_bytes(bytes[..4].try_into().ok()?) as usize;
-                (idx, address_raw.to_string())
+        // A primer that lost or gained a base fails the Hamming gate; retry by edit distance.
+        let (core, primer_shifted) = match Oligo::strip_tagged_fuzzy(dna, primers, PRIMER_TOLERANCE) {
+            Some(core) => (core, false),
+            None => match Oligo::strip_tagged_indel(dna, primers, PRIMER_TOLERANCE) {
+                Some(core) => (core, true),
+                None => return DecodeOutcome::PrimerMismatch,
             },
-            None => {
-                // Slow Path: Address is damaged, attempt Viterbi heal
-                let healed_addr = DnaMapper::viterbi_correct(address_raw, start_base_addr)?;
-                let bytes = DnaMapper::decode_shard(&healed_addr, start_base_addr)?;
-                if bytes.len() < 4 { return None; }
-                let idx = u32::from_be_bytes(bytes[..4].try_into().ok()?) as usize;
-                (idx, healed_addr)
-            }
         };
 
-        // 5. Decode Payload (With Viterbi Fallback)
-        // CRITICAL: Use the last char of the *Corrected* Address as seed.
-        let last_addr_char = corrected_address_str.chars().last().unwrap_or('A');
-        let start_base_payload = Base::from_char(last_addr_char)?;
+        // 3. Resolve Address Seed (Chained: Forward Primer tail)
+        // The primers already matched; an unusable seed means the address cannot be read.
+        let start_base_addr = match seeds.address_seed(fp) {
+            Some(b) => b,
+            None => return DecodeOutcome::AddressFailure,
+        };
 
-        let try_decode_payload = |p_seq: &str| -> Option<Vec<u8>> {
+        let read_index = |bytes: Vec<u8>| -> Option<usize> {
+            if bytes.len() < 4 { return None; }
+            Some(u32::from_be_bytes(bytes[..4].try_into().ok()?) as usize)
+        };
+
+        let try_decode_payload = |p_seq: &str, start_base_payload: Base| -> Option<Vec<u8>> {
             let bytes = DnaMapper::decode_shard(p_seq, start_base_payload)?;
             if bytes.len() < 4 { return None; } // No CRC found
 
@@ -135,21 +227,157 @@ impl ParallelProcessor {
             }
         };
 
-        // Attempt A: Direct Decode (Fast, O(N))
-        if let Some(data) = try_decode_payload(payload_raw) {
-            return Some((block_id, index, data));
+        // Stages 4-5 on one core: Ok((index, data, address_healed, payload_healed)),
+        // or the outcome of the stage that gave up. `heal` enables the Viterbi fallbacks.
+        let decode_core = |core: &str, heal: bool| -> Result<(usize, Vec<u8>, bool, bool), DecodeOutcome> {
+            if core.len() < ADDRESS_BASE_LEN { return Err(DecodeOutcome::AddressFailure); }
+
+            let address_raw = &core[..ADDRESS_BASE_LEN];
+            let payload_raw = &core[ADDRESS_BASE_LEN..];
+
+            // 4. Decode Address (With Viterbi Fallback)
+            // We need the address to be valid to get the Index AND the start seed for payload.
+            let (index, corrected_address_str, address_healed) =
+                match DnaMapper::decode_shard(address_raw, start_base_addr).and_then(read_index) {
+                    // Fast Path: Address is clean
+                    Some(idx) => (idx, address_raw.to_string(), false),
+                    None if !heal => return Err(DecodeOutcome::AddressFailure),
+                    None => {
+                        // Slow Path: Address is damaged, attempt Viterbi heal
+                        let healed = DnaMapper::viterbi_correct(address_raw, start_base_addr).and_then(|healed_addr| {
+                            let idx = DnaMapper::decode_shard(&healed_addr, start_base_addr).and_then(read_index)?;
+                            Some((idx, healed_addr))
+                        });
+                        match healed {
+                            Some((idx, healed_addr)) => (idx, healed_addr, true),
+                            None => return Err(DecodeOutcome::AddressFailure),
+                        }
+                    }
+                };
+
+            // 5. Decode Payload (With Viterbi Fallback)
+            // CRITICAL: Use the last char of the *Corrected* Address as seed.
+            let start_base_payload = match seeds.payload_seed(&corrected_address_str) {
+                Some(b) => b,
+                None => return Err(DecodeOutcome::AddressFailure),
+            };
+
+            // Attempt A: Direct Decode (Fast, O(N))
+            if let Some(data) = try_decode_payload(payload_raw, start_base_payload) {
+                return Ok((index, data, address_healed, false));
+            }
+
+            // Attempt B: Viterbi Decode (Slow, O(N))
+            // If direct failed (Trellis violation OR CRC mismatch), try to heal.
+            let healed_payload = if heal { DnaMapper::viterbi_correct(payload_raw, start_base_payload) } else { None };
+            if let Some(healed_payload) = healed_payload {
+                if let Some(data) = try_decode_payload(&healed_payload, start_base_payload) {
+                    // Success: The Viterbi algorithm found the correct path!
+                    return Ok((index, data, address_healed, true));
+                }
+            }
+
+            Err(DecodeOutcome::CrcFailure) // Strand is FUBAR
+        };
+
+        let decoded = |(index, data, address_healed, payload_healed): (usize, Vec<u8>, bool, bool), indel_healed: bool| {
+            if is_provenance {
+                // Provenance strands share the pipeline but carry a stamp, not a shard.
+                return match Provenance::from_bytes(&data) {
+                    Some(p) if index == PROVENANCE_INDEX as usize => DecodeOutcome::Provenance(p),
+                    _ => DecodeOutcome::MalformedHeader,
+                };
+            }
+            if is_manifest {
+                return match index.checked_sub(MANIFEST_INDEX_BASE as usize) {
+                    Some(i) if i < MANIFEST_TOTAL_SHARDS => DecodeOutcome::Manifest { index: i, data },
+                    _ => DecodeOutcome::MalformedHeader,
+                };
+            }
+            let indel_healed = indel_healed || primer_shifted;
+            DecodeOutcome::Decoded { block_id, index, data, address_healed, payload_healed, indel_healed }
+        };
+
+        let failure = match decode_core(core, true) {
+            Ok(shard) => return decoded(shard, false),
+            Err(failure) => failure,
+        };
+
+        // 6. Indel Recovery (Last Resort)
+        // A net insertion or deletion shifts every later trit, so both Viterbi stages fail.
+        // Address + payload are whole bytes (6 bases each): rebuild the core at the nearest
+        // valid length and run each candidate through the stages again. Resync candidates
+        // are many and already frame-exact, so only the single Levenshtein path gets the
+        // substitution fallbacks as well. A core already on the byte grid has no net
+        // shift, so ordinary CRC failures skip this stage.
+        let target = (core.len() + 3) / 6 * 6;
+        if target == core.len() || target <= ADDRESS_BASE_LEN || core.len().abs_diff(target) > MAX_INDEL_SHIFT {
+            return failure;
         }
+        // Fixed seeds restart the trellis at the payload boundary; chained seeds run through.
+        let reseed = match seeds {
+            SeedPolicy::Chained => None,
+            SeedPolicy::Fixed { payload, .. } => Some((ADDRESS_BASE_LEN, payload)),
+        };
+        let candidates = DnaMapper::resync_candidates(core, start_base_addr, target).into_iter()
+        .map(|candidate| (candidate, false))
+        .chain(DnaMapper::viterbi_correct_indels(core, start_base_addr, target, reseed).map(|candidate| (candidate, true)));
 
-        // Attempt B: Viterbi Decode (Slow, O(N))
-        // If direct failed (Trellis violation OR CRC mismatch), try to heal.
-        if let Some(healed_payload) = DnaMapper::viterbi_correct(payload_raw, start_base_payload) {
-            if let Some(data) = try_decode_payload(&healed_payload) {
-                // Success: The Viterbi algorithm found the correct path!
-                return Some((block_id, index, data));
+        // An edit inside the address can verify under more than one index (the CRC only
+        // covers the payload), so prefer the candidate that agrees with the header.
+        let mut fallback = None;
+        for (candidate, heal) in candidates {
+            if let Ok(shard) = decode_core(&candidate, heal) {
+                if parsed.as_ref().is_none_or(|h| h.shard == shard.0) {
+                    return decoded(shard, true);
+                }
+                fallback.get_or_insert(shard);
             }
         }
+        match fallback {
+            Some(shard) => decoded(shard, true),
+            None => failure,
+        }
+    }
 
-        None // Strand is FUBAR
+    /// RESTORE (Consensus): Votes failed reads of one strand (primers stripped) into a
+    /// single consensus strand and runs it through `decode_strand`.
+    pub fn decode_consensus(
+        header: &str,
+        cores: &[String],
+        primers: (&str, &str),
+        seeds: SeedPolicy
+    ) -> DecodeOutcome {
+        let (fp, rp) = primers;
+        match consensus::call_consensus(cores, fp, seeds) {
+            Some(core) => Self::decode_strand(header, &format!("{}{}{}", fp, core, rp), primers, seeds),
+            None => DecodeOutcome::CrcFailure,
+        }
+    }
+
+    /// RESTORE (Multi-Tube): Decodes a strand against several primer pairs.
+    /// The first pair that gets past the primer gate owns the strand; orthogonal
+    /// primer sets guarantee at most one can match.
+    pub fn decode_strand_any(
+        header: &str,
+        dna: &str,
+        primer_sets: &[(String, String)],
+        seeds: SeedPolicy
+    ) -> DecodeOutcome {
+        // Orthogonality is a Hamming guarantee; the edit-distance primer fallback is
+        // looser, so a pair that passes the Hamming gate outright always wins.
+        let owner = primer_sets.iter()
+        .find(|(fp, rp)| Oligo::strip_tagged_fuzzy(dna, (fp.as_str(), rp.as_str()), PRIMER_TOLERANCE).is_some());
+        if let Some((fp, rp)) = owner {
+            return Self::decode_strand(header, dna, (fp.as_str(), rp.as_str()), seeds);
+        }
+        for (fp, rp) in primer_sets {
+            match Self::decode_strand(header, dna, (fp.as_str(), rp.as_str()), seeds) {
+                DecodeOutcome::PrimerMismatch => continue,
+                outcome => return outcome,
+            }
+        }
+        DecodeOutcome::PrimerMismatch
     }
 
     /// SEARCH: Filters a BATCH of soup strands for specific primers.
@@ -201,4 +429,102 @@ impl ParallelProcessor {
         })
         .collect()
     }
+
+    /// SIMULATE: Decay driven by a user-defined error profile.
+    /// Each surviving strand is emitted `coverage` times, every copy corrupted independently,
+    /// mirroring how a sequencer returns many noisy reads of the same molecule.
+    /// With `seed`, strand `i` of the batch draws from its own generator seeded with
+    /// `seed + i`, so the output does not depend on thread scheduling.
+    pub fn process_profile_batch(
+        batch: Vec<(String, String)>,
+        profile: &ErrorProfile,
+        seed: Option<u64>
+    ) -> Vec<String> {
+        batch.into_par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, (header, dna))| {
+            let mut rng = match seed {
+                Some(s) => StdRng::seed_from_u64(s.wrapping_add(i as u64)),
+                None => StdRng::from_rng(thread_rng()).expect("thread_rng never fails"),
+            };
+
+            // 1. Dropout (Erasure) + Coverage draw
+            let copies = if rng.gen_bool(profile.dropout) { 0 } else { profile.coverage.sample(&mut rng) };
+
+            // 2. Channel noise, independent per read
+            (0..copies)
+            .map(|_| format!("{}\n{}", header, profile.corrupt(&dna, &mut rng)))
+            .collect::<Vec<_>>()
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMERS: (&str, &str) = ("ACGTACGTACGTACGTACGT", "TGCATGCATGCATGCATGCA");
+
+    fn encoded_strand(shard: &[u8]) -> (String, String) {
+        let result = ParallelProcessor::process_block(&StrandHeader::new(4, 0), vec![shard.to_vec()], PRIMERS, SeedPolicy::Chained);
+        let mut lines = result.shards[0].fasta_entry.lines();
+        (lines.next().unwrap().to_string(), lines.next().unwrap().to_string())
+    }
+
+    #[test]
+    fn single_insertion_is_healed_by_the_indel_stage() {
+        let shard: Vec<u8> = (0..32u8).collect();
+        let (header, strand) = encoded_strand(&shard);
+        let mut noisy = strand.clone();
+        let at = PRIMERS.0.len() + ADDRESS_BASE_LEN + 40;
+        noisy.insert(at, if &strand[at..at + 1] == "A" { 'C' } else { 'A' });
+
+        match ParallelProcessor::decode_strand(&header, &noisy, PRIMERS, SeedPolicy::Chained) {
+            DecodeOutcome::Decoded { block_id, index, data, indel_healed, .. } => {
+                assert_eq!((block_id, index), (4, 0));
+                assert_eq!(data, shard);
+                assert!(indel_healed);
+            }
+            other => panic!("expected a decoded shard, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn grid_length_crc_failure_skips_the_indel_stage() {
+        let shard: Vec<u8> = (0..32u8).collect();
+        let (header, strand) = encoded_strand(&shard);
+        // Complement 60 payload bases: the core keeps its length, so this is a plain CRC failure.
+        let start = PRIMERS.0.len() + ADDRESS_BASE_LEN + 24;
+        let mut bases: Vec<char> = strand.chars().collect();
+        for b in &mut bases[start..start + 60] {
+            *b = match *b { 'A' => 'T', 'T' => 'A', 'C' => 'G', _ => 'C' };
+        }
+        let noisy: String = bases.into_iter().collect();
+
+        assert_eq!(ParallelProcessor::decode_strand(&header, &noisy, PRIMERS, SeedPolicy::Chained), DecodeOutcome::CrcFailure);
+    }
+
+    #[test]
+    fn seeded_profile_batches_are_reproducible() {
+        let profile = ErrorProfile::from_json(r#"{
+            "dropout": 0.2, "insertion": 0.01, "deletion": 0.01,
+            "substitution": { "A": { "C": 0.02 }, "G": { "T": 0.02 } },
+            "coverage": { "distribution": "poisson", "mean": 3.0 }
+        }"#).unwrap();
+        let batch: Vec<(String, String)> = (0..64)
+        .map(|i| (format!(">blk0_s{}", i), "ACGTAGCTAGTCGATCGACT".repeat(6)))
+        .collect();
+
+        let first = ParallelProcessor::process_profile_batch(batch.clone(), &profile, Some(42));
+        assert_eq!(first, ParallelProcessor::process_profile_batch(batch.clone(), &profile, Some(42)));
+        assert_ne!(first, ParallelProcessor::process_profile_batch(batch, &profile, Some(43)));
+    }
+
+    #[test]
+    fn identical_shards_are_reported_as_collisions() {
+        let shards = vec![vec![1u8; 16], vec![0u8; 16], vec![0u8; 16], vec![0u8; 16]];
+        let result = ParallelProcessor::process_block(&StrandHeader::new(0, 0), shards, PRIMERS, SeedPolicy::Chained);
+        assert_eq!(result.collisions, vec![(1, 2), (1, 3)]);
+    }
 }
@@ -0,0 +1,826 @@
+// src/pipeline.rs
+// STREAMING PIPELINE
+// The full archive pipeline as embeddable library types: `HelixEncoder` turns a byte
+// stream into FASTA, `HelixDecoder` turns (noisy, shuffled, duplicated) reads back
+// into bytes. The `helix` CLI is a thin wrapper over both.
+//
+// Compile, per 4MB chunk:
+//   Zstd -> AES-256-GCM (Argon2id master key + HKDF session key per block)
+//   -> Block: [OrigLen 8] [EncLen 8] [GlobalSalt 16] [BlockSalt 16] [Nonce 12] [Payload...]
+//   -> RS N+K (or LT droplets) -> CRC32 + Trellis DNA -> Stability check (re-roll the salts on failure)
+//
+// Every tube is led by provenance and manifest strands, then its contiguous block range.
+// Restore runs the steps in reverse, voting reads through `ConsensusPool` and writing
+// each block in order once N of its shards are trusted.
+//
+// Progress is reported through an optional hook receiving `PipelineEvent`s.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::io::{BufRead, Read, Write};
+use anyhow::{Result, bail};
+use rand::RngCore;
+use crate::consensus::{ConsensusPool, CoverageStats};
+use crate::dna_mapper::UnstableStrands;
+#[cfg(feature = "crypto")]
+use crate::crypto;
+use crate::fasta_header::{self, StrandHeader};
+use crate::fountain::{FountainCodec, RankTracker};
+use crate::manifest::{Codec, Compression, Manifest, ManifestCollector};
+use crate::oligo::{Oligo, SeedPolicy};
+use crate::parallel::ParallelProcessor;
+use crate::provenance::{Provenance, ProvenanceLedger, PROVENANCE_COPIES};
+use crate::rs_engine::{RedundancyManager, DEFAULT_DATA_SHARDS, DEFAULT_PARITY_SHARDS};
+use crate::stream_manager::fill_buffer;
+use crate::telemetry::{DecodeOutcome, DecodeTelemetry};
+use crate::tubes::tube_for_block;
+use crate::STREAMING_CHUNK_SIZE;
+
+/// Zstd level used for every block (recorded in the manifest).
+pub const DEFAULT_ZSTD_LEVEL: u8 = 3;
+
+/// Salt re-rolls per block before an unstable block halts compilation.
+pub const DEFAULT_MAX_RETRIES: usize = 5;
+
+/// `[OrigLen 8] [EncLen 8] [GlobalSalt 16] [BlockSalt 16] [Nonce 12]`
+const BLOCK_HEADER_LEN: usize = 60;
+
+/// What compile does when a block keeps producing unstable strands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StabilityPolicy {
+    /// Encoding attempts per block (each with fresh salts).
+    pub max_retries: usize,
+    /// Write the last attempt anyway instead of halting.
+    pub force: bool,
+}
+
+impl Default for StabilityPolicy {
+    fn default() -> Self {
+        Self { max_retries: DEFAULT_MAX_RETRIES, force: false }
+    }
+}
+
+/// Codec parameters shared by `HelixEncoder` and `HelixDecoder`.
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    pub codec: Codec,
+    /// Reed-Solomon: N data + K parity shards. Fountain: N source symbols + at least
+    /// K surplus droplets.
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    /// Archive tag written into every data strand header.
+    pub tag: String,
+    /// One primer pair per tube. Compile writes tube `t` under `primer_sets[t]`;
+    /// restore accepts reads amplified by any of them.
+    pub primer_sets: Vec<(String, String)>,
+    /// Enables Argon2id/HKDF/AES-256-GCM (needs the `crypto` feature).
+    pub password: Option<String>,
+    pub seeds: SeedPolicy,
+    pub compression: Compression,
+    pub stability: StabilityPolicy,
+    /// Physical copies emitted per strand (compile).
+    pub copies: usize,
+    /// Reads a strand needs before its shard is trusted (restore).
+    pub min_coverage: usize,
+}
+
+impl PipelineConfig {
+    /// Defaults for a single tube: the tag's primers, Reed-Solomon 10+5, chained seeds,
+    /// zstd (when compiled in) and no encryption.
+    pub fn new(tag: &str) -> Self {
+        Self {
+            codec: Codec::ReedSolomon,
+            data_shards: DEFAULT_DATA_SHARDS,
+            parity_shards: DEFAULT_PARITY_SHARDS,
+            tag: tag.to_string(),
+            primer_sets: vec![Oligo::resolve_primers(tag, None, None)],
+            password: None,
+            seeds: SeedPolicy::Chained,
+            compression: if cfg!(feature = "compression") {
+                Compression::Zstd { level: DEFAULT_ZSTD_LEVEL }
+            } else {
+                Compression::None
+            },
+            stability: StabilityPolicy::default(),
+            copies: 1,
+            min_coverage: 1,
+        }
+    }
+
+    /// Rejects configurations the pipeline cannot run, before any output is written.
+    pub fn validate(&self) -> Result<()> {
+        if self.primer_sets.is_empty() {
+            bail!("At least one primer pair is required.");
+        }
+        for (fp, rp) in &self.primer_sets {
+            Oligo::validate_primers((fp, rp))?;
+        }
+        BlockCodec::new(self)?;
+        if self.copies == 0 || self.min_coverage == 0 {
+            bail!("Copies and minimum coverage must be at least 1.");
+        }
+        if self.password.is_some() && !cfg!(feature = "crypto") {
+            bail!("Encryption requires helix to be built with the `crypto` feature.");
+        }
+        if matches!(self.compression, Compression::Zstd { .. }) && !cfg!(feature = "compression") {
+            bail!("Zstd requires helix to be built with the `compression` feature.");
+        }
+        Ok(())
+    }
+}
+
+/// Restore failures that callers may want to tell apart from I/O errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineError {
+    /// AES-GCM rejected a reconstructed block: wrong password or tampered strands.
+    Decryption { block_id: u32 },
+    /// A reconstructed block header is inconsistent, usually a wrong codec geometry.
+    CorruptBlock { block_id: u32, codec: String, data_shards: usize, parity_shards: usize },
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Decryption { block_id } => write!(f, "Decryption failed for Block {}.", block_id),
+            PipelineError::CorruptBlock { block_id, codec, data_shards, parity_shards } => write!(f,
+                "Block {}: Reconstructed header is inconsistent. Was the archive compiled with {} Config {}+{}?",
+                block_id, codec, data_shards, parity_shards),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+/// Progress reported while a pipeline runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineEvent {
+    /// Metadata strands are written; block encoding begins.
+    /// `blocks` is `None` when the input length was not supplied.
+    ArchiveStarted { archive_id: String, tool_version: String, blocks: Option<u64> },
+    /// Argon2id master key derivation began (slow by design).
+    DerivingKey,
+    KeyDerived,
+    /// One encoding attempt of a block finished its stability analysis.
+    BlockAttempt { block_id: u32, bytes: usize, avg_gc: f64, avg_tm: f64, attempt: usize },
+    /// Shard pairs of a block with identical bytes (degenerate input).
+    PayloadCollisions { block_id: u32, pairs: Vec<(usize, usize)> },
+    /// Retries ran out and `force` wrote the block with unstable strands.
+    ForcedUnstable { block_id: u32, unstable: usize },
+    /// A block's strands were written to its tube.
+    BlockEncoded { block_id: u32, tube: usize, strands: u64 },
+    /// A block was reconstructed, decrypted and decompressed.
+    BlockRecovered { block_id: u32, bytes: usize },
+}
+
+type ProgressHook = Box<dyn FnMut(&PipelineEvent) + Send>;
+
+fn emit(progress: &mut Option<ProgressHook>, event: PipelineEvent) {
+    if let Some(hook) = progress.as_mut() {
+        hook(&event);
+    }
+}
+
+/// Totals of a compile run.
+#[derive(Debug, Clone)]
+pub struct CompileSummary {
+    pub provenance: Provenance,
+    pub manifest: Manifest,
+    /// Plaintext bytes read (differs from `manifest.file_len` if the input changed).
+    pub total_bytes: u64,
+    /// Block bytes after compression, encryption and headers (before redundancy).
+    pub encoded_bytes: u64,
+    pub blocks: u32,
+    /// Data strands written, copies included.
+    pub strands: u64,
+    /// Block IDs written to each tube.
+    pub tube_blocks: Vec<Vec<u32>>,
+}
+
+/// COMPILE: bytes -> FASTA.
+pub struct HelixEncoder {
+    config: PipelineConfig,
+    file_name: String,
+    input_len: Option<u64>,
+    progress: Option<ProgressHook>,
+}
+
+impl HelixEncoder {
+    pub fn new(config: PipelineConfig) -> Self {
+        Self { config, file_name: String::new(), input_len: None, progress: None }
+    }
+
+    /// Original file name recorded in the manifest.
+    pub fn with_file_name(mut self, name: impl Into<String>) -> Self {
+        self.file_name = name.into();
+        self
+    }
+
+    /// Plaintext length of the input, if known. The manifest leads every tube, so the
+    /// length must be known before the first block: without it the manifest records an
+    /// unknown length and block count, and only a single tube can be written.
+    pub fn with_input_len(mut self, len: u64) -> Self {
+        self.input_len = Some(len);
+        self
+    }
+
+    pub fn on_progress(mut self, hook: impl FnMut(&PipelineEvent) + Send + 'static) -> Self {
+        self.progress = Some(Box::new(hook));
+        self
+    }
+
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    /// Compiles `reader` (to EOF) into a single tube. Any stream works, pipes included.
+    pub fn compile<R: Read, W: Write>(&mut self, reader: R, writer: W) -> Result<CompileSummary> {
+        self.compile_tubes(reader, &mut [writer])
+    }
+
+    /// Compiles `reader` across one writer per primer set, in contiguous block ranges.
+    /// Splitting needs the block count up front, so more than one tube requires `with_input_len`.
+    pub fn compile_tubes<R: Read, W: Write>(&mut self, mut reader: R, writers: &mut [W]) -> Result<CompileSummary> {
+        let config = &self.config;
+        let progress = &mut self.progress;
+        config.validate()?;
+        if writers.len() != config.primer_sets.len() {
+            bail!("{} primer sets configured for {} tube writers.", config.primer_sets.len(), writers.len());
+        }
+        let tube_count = writers.len();
+
+        let input_len = self.input_len;
+        let estimated_blocks = input_len.map(|len| len.div_ceil(STREAMING_CHUNK_SIZE as u64));
+        if tube_count > 1 && estimated_blocks.is_none() {
+            bail!("Splitting an archive across {} tubes needs the input length up front.", tube_count);
+        }
+        let codec = BlockCodec::new(config)?;
+
+        // Provenance: stamp every tube with this run's UUID before any data.
+        let provenance = Provenance::generate_stable(&config.primer_sets, config.seeds, config.stability.force)?;
+        for (t, (writer, (fp, rp))) in writers.iter_mut().zip(config.primer_sets.iter()).enumerate() {
+            let entry = provenance.for_tube(t as u16).to_fasta_entry((fp.as_str(), rp.as_str()));
+            for _ in 0..PROVENANCE_COPIES {
+                writer.write_all(entry.as_bytes())?;
+            }
+        }
+
+        // Manifest: codec parameters, so restore needs nothing but the primers.
+        // Blocks are read with `fill_buffer`, so a known length gives the exact block count.
+        let manifest = Manifest {
+            archive_id: provenance.archive_id,
+            codec: config.codec,
+            data_shards: config.data_shards,
+            // Fountain layouts may extend past the requested surplus; record the real count.
+            parity_shards: codec.total_shards() - config.data_shards,
+            chunk_size: STREAMING_CHUNK_SIZE as u32,
+            compression: config.compression,
+            encrypted: config.password.is_some(),
+            file_name: self.file_name.clone(),
+            file_len: input_len,
+            total_blocks: estimated_blocks.map(|b| b as u32),
+            tubes: tube_count as u16,
+            salt: 0,
+        }.stabilize(&config.primer_sets, config.stability.force)?;
+        for (writer, (fp, rp)) in writers.iter_mut().zip(config.primer_sets.iter()) {
+            for entry in manifest.to_fasta_entries((fp, rp))? {
+                writer.write_all(entry.as_bytes())?;
+            }
+        }
+        emit(progress, PipelineEvent::ArchiveStarted {
+            archive_id: provenance.uuid(),
+            tool_version: provenance.tool_version.clone(),
+            blocks: estimated_blocks,
+        });
+
+        // Master Key: derived once, salted per run; blocks get HKDF session keys.
+        let mut master_key = [0u8; 32];
+        let mut global_salt = [0u8; 16];
+        if let Some(pass) = &config.password {
+            emit(progress, PipelineEvent::DerivingKey);
+            rand::thread_rng().fill_bytes(&mut global_salt);
+            master_key = derive_master_key(pass, &global_salt)?;
+            emit(progress, PipelineEvent::KeyDerived);
+        }
+
+        let total_shards = codec.total_shards() as f64;
+        let mut buffer = vec![0u8; STREAMING_CHUNK_SIZE];
+        let mut summary = CompileSummary {
+            provenance: provenance.clone(),
+            manifest,
+            total_bytes: 0,
+            encoded_bytes: 0,
+            blocks: 0,
+            strands: 0,
+            tube_blocks: vec![Vec::new(); tube_count],
+        };
+
+        loop {
+            let bytes_read = fill_buffer(&mut reader, &mut buffer)?;
+            if bytes_read == 0 { break; }
+            let block_id = summary.blocks;
+            summary.total_bytes += bytes_read as u64;
+
+            // Compression is deterministic: once per block, outside the retry loop.
+            let compressed = compress(&buffer[..bytes_read], config.compression)?;
+
+            let tube = tube_for_block(block_id, estimated_blocks.unwrap_or(0), tube_count);
+            let tube_primers = (config.primer_sets[tube].0.as_str(), config.primer_sets[tube].1.as_str());
+            summary.tube_blocks[tube].push(block_id);
+            let header_template = StrandHeader {
+                tag: Some(config.tag.clone()),
+                frag: Some((tube as u16, tube_count as u16)),
+                ..StrandHeader::new(block_id, 0)
+            };
+
+            // RETRY LOOP: Salt Rotation
+            // If the resulting DNA is unstable (high GC/bad Tm), we re-roll the Block Salt.
+            // This changes the encryption ciphertext, which changes the DNA sequence.
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+
+                let mut nonce_bytes = [0u8; 12];
+                let mut block_salt = [0u8; 16];
+                rand::thread_rng().fill_bytes(&mut nonce_bytes);
+                rand::thread_rng().fill_bytes(&mut block_salt);
+
+                let payload = match config.password {
+                    Some(_) => encrypt_block(&master_key, &block_salt, &nonce_bytes, &compressed)?,
+                    None => compressed.clone(),
+                };
+
+                let mut block = (bytes_read as u64).to_be_bytes().to_vec();
+                block.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+                block.extend_from_slice(&global_salt);
+                block.extend_from_slice(&block_salt);
+                block.extend_from_slice(&nonce_bytes);
+                block.extend_from_slice(&payload);
+
+                let shards = codec.encode(&block)?;
+                let result = ParallelProcessor::process_block(&header_template, shards, tube_primers, config.seeds);
+
+                let unstable = result.shards.iter().filter(|r| !r.stability.is_stable).count();
+                let avg_gc = result.shards.iter().map(|r| r.stability.gc_content).sum::<f64>() / total_shards;
+                let avg_tm = result.shards.iter().map(|r| r.stability.melting_temp).sum::<f64>() / total_shards;
+                emit(progress, PipelineEvent::BlockAttempt { block_id, bytes: bytes_read, avg_gc, avg_tm, attempt });
+                if !result.collisions.is_empty() {
+                    emit(progress, PipelineEvent::PayloadCollisions { block_id, pairs: result.collisions.clone() });
+                }
+
+                if unstable > 0 && attempt < config.stability.max_retries {
+                    continue;
+                }
+                if unstable > 0 {
+                    if !config.stability.force {
+                        let scope = format!("Block {}", block_id);
+                        return Err(UnstableStrands { scope, unstable, retries: attempt }.into());
+                    }
+                    emit(progress, PipelineEvent::ForcedUnstable { block_id, unstable });
+                }
+
+                let mut strands = 0u64;
+                for entry in ParallelProcessor::replicate_block(&result.shards, config.copies) {
+                    writers[tube].write_all(entry.as_bytes())?;
+                    strands += 1;
+                }
+                summary.encoded_bytes += block.len() as u64;
+                summary.strands += strands;
+                emit(progress, PipelineEvent::BlockEncoded { block_id, tube, strands });
+                break;
+            }
+            summary.blocks += 1;
+        }
+
+        for writer in writers.iter_mut() {
+            writer.flush()?;
+        }
+        Ok(summary)
+    }
+}
+
+/// Totals and loose ends of a restore run.
+#[derive(Debug, Default)]
+pub struct RestoreSummary {
+    /// Trusted shards handed to the block assembler (late arrivals included).
+    pub shards_found: u64,
+    pub telemetry: DecodeTelemetry,
+    pub coverage: CoverageStats,
+    pub provenance: ProvenanceLedger,
+    pub recovered_blocks: BTreeSet<u32>,
+    /// Blocks with some shards, but too few to reconstruct.
+    pub partial_blocks: Vec<u32>,
+    /// Recovered blocks held back because an earlier block never arrived.
+    pub pending_blocks: Vec<u32>,
+    /// The block the output stream stopped at (the first gap, if any).
+    pub next_block: u32,
+    pub bytes_written: u64,
+    /// Surplus shards for already recovered blocks, and how many disagreed with them.
+    pub late_shards: u64,
+    pub late_conflicts: u64,
+    pub conflicting_blocks: BTreeSet<u32>,
+}
+
+impl RestoreSummary {
+    /// Every block that showed up was recovered and written without gaps.
+    /// Whether blocks are missing entirely is for the manifest to say.
+    pub fn is_complete(&self) -> bool {
+        self.partial_blocks.is_empty() && self.pending_blocks.is_empty()
+    }
+}
+
+/// RESTORE: reads -> bytes.
+pub struct HelixDecoder {
+    config: PipelineConfig,
+    progress: Option<ProgressHook>,
+}
+
+impl HelixDecoder {
+    pub fn new(config: PipelineConfig) -> Self {
+        Self { config, progress: None }
+    }
+
+    pub fn on_progress(mut self, hook: impl FnMut(&PipelineEvent) + Send + 'static) -> Self {
+        self.progress = Some(Box::new(hook));
+        self
+    }
+
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    pub fn restore<R: BufRead, W: Write>(&mut self, reader: R, writer: W) -> Result<RestoreSummary> {
+        self.restore_tubes([reader], writer)
+    }
+
+    /// Streams the tubes back to back; contiguous partitioning keeps blocks in order.
+    /// Errors are reserved for I/O, decryption and geometry faults; missing data is
+    /// reported in the summary.
+    pub fn restore_tubes<R: BufRead, W: Write>(&mut self, readers: impl IntoIterator<Item = R>, writer: W) -> Result<RestoreSummary> {
+        let config = &self.config;
+        let progress = &mut self.progress;
+        config.validate()?;
+        let codec = BlockCodec::new(config)?;
+        let total_shards = codec.total_shards();
+
+        let mut assembler = BlockAssembler::new(config, codec, writer);
+        let mut consensus = ConsensusPool::new(&config.primer_sets, config.seeds, total_shards, config.min_coverage);
+        let mut telemetry = DecodeTelemetry::new();
+        let mut provenance = ProvenanceLedger::new();
+
+        let mut lines = readers.into_iter().flat_map(|r| r.lines());
+        while let Some(header) = lines.next().transpose()? {
+            if !header.starts_with('>') { continue; }
+            let Some(dna) = lines.next().transpose()? else { break };
+
+            // Geometry Gate: a healed address may decode to any u32; never let it
+            // allocate a block slot.
+            let outcome = ParallelProcessor::decode_strand_any(&header, &dna, &config.primer_sets, config.seeds)
+            .with_shard_limit(total_shards);
+            telemetry.record(&outcome);
+            if let DecodeOutcome::Provenance(p) = &outcome {
+                provenance.record(p);
+            }
+
+            // Consensus Pool: verified reads pass once their strand has enough coverage;
+            // failed reads are pooled and voted per strand.
+            let shard = match outcome {
+                DecodeOutcome::Decoded { block_id, index, data, .. } => consensus.add_verified(&header, &dna, block_id, index, data),
+                DecodeOutcome::CrcFailure | DecodeOutcome::AddressFailure | DecodeOutcome::IndexOutOfRange { .. } => {
+                    consensus.add_failed(&header, &dna)
+                }
+                _ => None,
+            };
+            if let Some((block_id, index, data)) = shard {
+                if let Some(done) = assembler.insert(block_id, index, data, progress)? {
+                    consensus.retire_block(done);
+                }
+            }
+        }
+
+        // End of stream: a last vote for every strand that never verified.
+        for (block_id, index, data) in consensus.flush() {
+            if let Some(done) = assembler.insert(block_id, index, data, progress)? {
+                consensus.retire_block(done);
+            }
+        }
+
+        assembler.writer.flush()?;
+        Ok(RestoreSummary {
+            telemetry,
+            coverage: consensus.stats(),
+            provenance,
+            ..assembler.into_summary()
+        })
+    }
+}
+
+/// Collects trusted shards per block; once N are in, reconstructs, decrypts,
+/// decompresses and writes the block (in order).
+struct BlockAssembler<'a, W> {
+    config: &'a PipelineConfig,
+    codec: BlockCodec,
+    writer: W,
+    active: HashMap<u32, HashMap<usize, Vec<u8>>>,
+    decoded: BTreeMap<u32, Vec<u8>>,
+    /// CRC32 of every N+K shard of each recovered block.
+    /// Lets late arrivals be verified without keeping whole shards in RAM.
+    fingerprints: HashMap<u32, Vec<u32>>,
+    master_key: Option<[u8; 32]>,
+    summary: RestoreSummary,
+}
+
+impl<'a, W: Write> BlockAssembler<'a, W> {
+    fn new(config: &'a PipelineConfig, codec: BlockCodec, writer: W) -> Self {
+        Self {
+            config,
+            codec,
+            writer,
+            active: HashMap::new(),
+            decoded: BTreeMap::new(),
+            fingerprints: HashMap::new(),
+            master_key: None,
+            summary: RestoreSummary::default(),
+        }
+    }
+
+    /// Files one trusted shard. Returns the block ID if this completed a block.
+    fn insert(&mut self, block_id: u32, index: usize, shard: Vec<u8>, progress: &mut Option<ProgressHook>) -> Result<Option<u32>> {
+        self.summary.shards_found += 1;
+
+        if let Some(fingerprints) = self.fingerprints.get(&block_id) {
+            // LATE ARRIVAL: Cross-check the shard against the reconstructed version; a
+            // mismatch means the pool holds two archives under the same primers/block ID.
+            self.summary.late_shards += 1;
+            if fingerprints[index] != crc32fast::hash(&shard) {
+                self.summary.late_conflicts += 1;
+                self.summary.conflicting_blocks.insert(block_id);
+            }
+            return Ok(None);
+        }
+
+        let shards = self.active.entry(block_id).or_default();
+        shards.insert(index, shard);
+        let Some((raw_block, full_shards)) = self.codec.decode(block_id, index, shards) else { return Ok(None) };
+
+        let plain = self.open_block(block_id, &raw_block, progress)?;
+        let bytes = plain.len();
+        self.decoded.insert(block_id, plain);
+        self.active.remove(&block_id);
+        self.fingerprints.insert(block_id, full_shards.iter().map(|s| crc32fast::hash(s)).collect());
+        self.summary.recovered_blocks.insert(block_id);
+        emit(progress, PipelineEvent::BlockRecovered { block_id, bytes });
+
+        // Write ordered blocks
+        while let Some(ready) = self.decoded.remove(&self.summary.next_block) {
+            self.writer.write_all(&ready)?;
+            self.summary.bytes_written += ready.len() as u64;
+            self.summary.next_block += 1;
+        }
+        Ok(Some(block_id))
+    }
+
+    /// Parses the block header, then decrypts and decompresses the payload.
+    /// Bounds-checked: a wrong RS geometry still "reconstructs", just into garbage.
+    fn open_block(&mut self, block_id: u32, raw: &[u8], progress: &mut Option<ProgressHook>) -> Result<Vec<u8>> {
+        let geometry_error = || anyhow::Error::new(PipelineError::CorruptBlock {
+            block_id,
+            codec: self.codec.to_string(),
+            data_shards: self.config.data_shards,
+            parity_shards: self.config.parity_shards,
+        });
+        if raw.len() < BLOCK_HEADER_LEN { return Err(geometry_error()); }
+        let orig_len = u64::from_be_bytes(raw[0..8].try_into()?) as usize;
+        let enc_len = u64::from_be_bytes(raw[8..16].try_into()?) as usize;
+        let global_salt = &raw[16..32];
+        let block_salt = &raw[32..48];
+        let nonce_bytes = &raw[48..60];
+        let mut payload = raw.get(BLOCK_HEADER_LEN..BLOCK_HEADER_LEN.saturating_add(enc_len))
+        .ok_or_else(geometry_error)?
+        .to_vec();
+
+        if let Some(pass) = &self.config.password {
+            // The master key is salted per run; derive it once, on the first block.
+            let master_key = match self.master_key {
+                Some(key) => key,
+                None => {
+                    emit(progress, PipelineEvent::DerivingKey);
+                    let key = derive_master_key(pass, global_salt)?;
+                    emit(progress, PipelineEvent::KeyDerived);
+                    *self.master_key.insert(key)
+                }
+            };
+            payload = match decrypt_block(&master_key, block_salt, nonce_bytes, &payload) {
+                Some(p) => p,
+                None => return Err(PipelineError::Decryption { block_id }.into()),
+            };
+        }
+
+        let plain = decompress(payload, self.config.compression)?;
+        Ok(plain.get(..orig_len).ok_or_else(geometry_error)?.to_vec())
+    }
+
+    fn into_summary(mut self) -> RestoreSummary {
+        self.summary.partial_blocks = self.active.keys().copied().collect();
+        self.summary.partial_blocks.sort_unstable();
+        self.summary.pending_blocks = self.decoded.keys().copied().collect();
+        self.summary
+    }
+}
+
+/// The erasure code a pipeline runs, built once per run.
+enum BlockCodec {
+    ReedSolomon(RedundancyManager),
+    /// Droplet rank of every block still being collected.
+    Fountain(FountainCodec, HashMap<u32, RankTracker>),
+}
+
+impl BlockCodec {
+    fn new(config: &PipelineConfig) -> Result<Self> {
+        Ok(match config.codec {
+            Codec::ReedSolomon => BlockCodec::ReedSolomon(RedundancyManager::new(config.data_shards, config.parity_shards)?),
+            Codec::Fountain => BlockCodec::Fountain(FountainCodec::new(config.data_shards, config.parity_shards)?, HashMap::new()),
+        })
+    }
+
+    /// Strands per block: N+K shards, or every droplet.
+    fn total_shards(&self) -> usize {
+        match self {
+            BlockCodec::ReedSolomon(rs) => rs.total_shards(),
+            BlockCodec::Fountain(lt, _) => lt.droplets(),
+        }
+    }
+
+    fn encode(&self, block: &[u8]) -> Result<Vec<Vec<u8>>> {
+        match self {
+            BlockCodec::ReedSolomon(rs) => rs.encode_to_shards(block),
+            BlockCodec::Fountain(lt, _) => lt.encode_to_droplets(block),
+        }
+    }
+
+    /// Rebuilds a block once shard `index` makes its trusted shards sufficient. Returns the
+    /// block (with trailing padding) and every shard it emits, for cross-checking late arrivals.
+    fn decode(&mut self, block_id: u32, index: usize, shards: &HashMap<usize, Vec<u8>>) -> Option<(Vec<u8>, Vec<Vec<u8>>)> {
+        match self {
+            BlockCodec::ReedSolomon(rs) => {
+                if shards.len() < rs.data_shards() { return None; }
+                let slots = (0..rs.total_shards()).map(|i| shards.get(&i).cloned()).collect();
+                let full = rs.reconstruct_all(slots).ok()?;
+                Some((rs.flatten_data(&full), full))
+            }
+            BlockCodec::Fountain(lt, ranks) => {
+                if !ranks.entry(block_id).or_insert_with(|| RankTracker::new(lt)).insert(lt, index) { return None; }
+                let symbols = lt.recover_symbols(shards).ok()?;
+                ranks.remove(&block_id);
+                let droplets = (0..lt.droplets()).map(|i| lt.droplet(i, &symbols)).collect();
+                Some((symbols.concat(), droplets))
+            }
+        }
+    }
+}
+
+impl fmt::Display for BlockCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockCodec::ReedSolomon(_) => write!(f, "RS"),
+            BlockCodec::Fountain(..) => write!(f, "Fountain"),
+        }
+    }
+}
+
+/// Finds the provenance stamp and rebuilds the manifest from the metadata strands of
+/// `readers` (data strands are skipped). Both lead every tube, so this normally stops
+/// within the first few records; archives without them are scanned to the end.
+pub fn scan_metadata<R: BufRead>(readers: impl IntoIterator<Item = R>, primer_sets: &[(String, String)]) -> Result<(Option<Provenance>, Option<Manifest>)> {
+    let mut stamp = None;
+    let mut manifest = ManifestCollector::new();
+    for reader in readers {
+        let mut lines = reader.lines();
+        while let Some(header) = lines.next().transpose()? {
+            if !fasta_header::is_provenance(&header) && !fasta_header::is_manifest(&header) { continue; }
+            let Some(dna) = lines.next().transpose()? else { break };
+            match ParallelProcessor::decode_strand_any(&header, &dna, primer_sets, SeedPolicy::Chained) {
+                DecodeOutcome::Provenance(p) => { stamp.get_or_insert(p); }
+                DecodeOutcome::Manifest { index, data } => { manifest.insert(index, data)?; }
+                _ => {}
+            }
+            if stamp.is_some() && manifest.manifest().is_some() {
+                return Ok((stamp, manifest.into_manifest()));
+            }
+        }
+    }
+    Ok((stamp, manifest.into_manifest()))
+}
+
+fn compress(data: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        #[cfg(feature = "compression")]
+        Compression::Zstd { level } => Ok(zstd::encode_all(data, level as i32)?),
+        #[cfg(not(feature = "compression"))]
+        Compression::Zstd { .. } => bail!("Zstd requires helix to be built with the `compression` feature."),
+    }
+}
+
+fn decompress(data: Vec<u8>, compression: Compression) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data),
+        #[cfg(feature = "compression")]
+        Compression::Zstd { .. } => Ok(zstd::decode_all(&*data)?),
+        #[cfg(not(feature = "compression"))]
+        Compression::Zstd { .. } => bail!("Zstd requires helix to be built with the `compression` feature."),
+    }
+}
+
+#[cfg(feature = "crypto")]
+fn derive_master_key(password: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    crypto::derive_master_key(password, salt)
+}
+
+#[cfg(feature = "crypto")]
+fn encrypt_block(master_key: &[u8], block_salt: &[u8], nonce: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+    crypto::encrypt_block(master_key, block_salt, nonce, payload)
+}
+
+#[cfg(feature = "crypto")]
+fn decrypt_block(master_key: &[u8], block_salt: &[u8], nonce: &[u8], payload: &[u8]) -> Option<Vec<u8>> {
+    crypto::decrypt_block(master_key, block_salt, nonce, payload)
+}
+
+// Without `crypto`, `PipelineConfig::validate` rejects passwords before these are reached.
+#[cfg(not(feature = "crypto"))]
+fn derive_master_key(_password: &str, _salt: &[u8]) -> Result<[u8; 32]> {
+    bail!("Encryption requires helix to be built with the `crypto` feature.")
+}
+
+#[cfg(not(feature = "crypto"))]
+fn encrypt_block(_master_key: &[u8], _block_salt: &[u8], _nonce: &[u8], _payload: &[u8]) -> Result<Vec<u8>> {
+    bail!("Encryption requires helix to be built with the `crypto` feature.")
+}
+
+#[cfg(not(feature = "crypto"))]
+fn decrypt_block(_master_key: &[u8], _block_salt: &[u8], _nonce: &[u8], _payload: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn encoder_and_decoder_can_move_to_worker_threads() {
+        assert_send::<HelixEncoder>();
+        assert_send::<HelixDecoder>();
+    }
+
+    #[test]
+    fn compiles_from_a_stream_of_unknown_length() {
+        let data: Vec<u8> = (0..5000u32).map(|i| (i * 31 % 251) as u8).collect();
+        let config = PipelineConfig::new("stream");
+        let mut archive = Vec::new();
+        let summary = HelixEncoder::new(config.clone()).compile(data.as_slice(), &mut archive).unwrap();
+        assert_eq!((summary.manifest.file_len, summary.manifest.total_blocks), (None, None));
+        assert_eq!((summary.total_bytes, summary.blocks), (5000, 1));
+
+        let mut restored = Vec::new();
+        let result = HelixDecoder::new(config).restore(archive.as_slice(), &mut restored).unwrap();
+        assert_eq!(result.recovered_blocks.len(), 1);
+        assert_eq!(restored, data);
+    }
+
+    #[test]
+    fn supplied_length_is_recorded_in_the_manifest() {
+        let data = vec![7u8; 300];
+        let mut archive = Vec::new();
+        let summary = HelixEncoder::new(PipelineConfig::new("sized"))
+        .with_input_len(300)
+        .compile(data.as_slice(), &mut archive)
+        .unwrap();
+        assert_eq!((summary.manifest.file_len, summary.manifest.total_blocks), (Some(300), Some(1)));
+    }
+
+    #[test]
+    fn read_errors_abort_the_restore() {
+        let config = PipelineConfig::new("io");
+        let mut archive = Vec::new();
+        HelixEncoder::new(config.clone()).compile([5u8; 400].as_slice(), &mut archive).unwrap();
+        // Invalid UTF-8 halfway through the pool must not pass as end of stream.
+        let cut = archive.len() / 2;
+        archive.splice(cut..cut, [b'\n', 0xFF, b'\n']);
+
+        let result = HelixDecoder::new(config).restore(archive.as_slice(), &mut Vec::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn splitting_tubes_needs_the_input_length() {
+        let (fp, rp) = Oligo::resolve_primers("split", None, None);
+        let config = PipelineConfig {
+            primer_sets: Oligo::orthogonal_primer_set((&fp, &rp), 2).unwrap(),
+            ..PipelineConfig::new("split")
+        };
+        let mut tubes = [Vec::new(), Vec::new()];
+        assert!(HelixEncoder::new(config).compile_tubes([1u8, 2, 3].as_slice(), &mut tubes).is_err());
+    }
+}
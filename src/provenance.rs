@@ -0,0 +1,251 @@
+// src/provenance.rs
+// ARCHIVE PROVENANCE
+// Every compile run stamps its output with a random archive UUID, creation time
+// and tool version, carried by dedicated provenance strands in every tube.
+// Physically mixed or relabeled tubes can always be traced back to their run.
+//
+// Strand: [FP] [Address = PROVENANCE_INDEX] [CRC32 + Record] [RP]
+// Record: [Magic "HXPV" 4] [Ver 1] [UUID 16] [Created u64 8] [Tube u16 2] [VerLen 1] [Version...] [SeedPolicy 1|3]
+//
+// Provenance strands always use chained seeds, so they are readable before the
+// archive's own (recorded) seed policy is known.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use rand::RngCore;
+use crate::dna_mapper::{DnaMapper, UnstableStrands};
+use crate::oligo::{Oligo, SeedPolicy};
+
+/// FASTA header (without `>`) used by provenance strands; never starts with `blk`.
+pub const PROVENANCE_HEADER: &str = "helix_prov";
+
+/// Reserved address index; data shards never get near it.
+pub const PROVENANCE_INDEX: u32 = u32::MAX;
+
+/// Copies emitted per tube, so a single dropout cannot erase the stamp.
+pub const PROVENANCE_COPIES: usize = 3;
+
+const MAGIC: &[u8; 4] = b"HXPV";
+const RECORD_VERSION: u8 = 1;
+const MAX_STABILITY_RETRIES: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Provenance {
+    pub archive_id: [u8; 16],
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    pub tube: u16,
+    pub tool_version: String,
+    /// Trellis seed policy used for the archive's data strands.
+    pub seed_policy: SeedPolicy,
+}
+
+impl Provenance {
+    /// Stamps a new compile run: random (v4) UUID, current time, this crate's version.
+    pub fn generate(seed_policy: SeedPolicy) -> Self {
+        let mut archive_id = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut archive_id);
+        archive_id[6] = (archive_id[6] & 0x0F) | 0x40; // Version 4
+        archive_id[8] = (archive_id[8] & 0x3F) | 0x80; // RFC 4122 variant
+
+        let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+        Self {
+            archive_id,
+            created_at,
+            tube: 0,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            seed_policy,
+        }
+    }
+
+    /// Like `generate`, but re-rolls the UUID until every tube's stamp strand passes
+    /// the synthesis stability check (the stamp is the only unwhitened strand we emit).
+    /// Fails if no roll qualifies, unless `force` accepts the last one.
+    pub fn generate_stable(primer_sets: &[(String, String)], seed_policy: SeedPolicy, force: bool) -> Result<Self> {
+        let unstable_tubes = |stamp: &Self| primer_sets.iter().enumerate().filter(|(t, (fp, rp))| {
+            let entry = stamp.for_tube(*t as u16).to_fasta_entry((fp.as_str(), rp.as_str()));
+            let strand = entry.lines().nth(1).unwrap_or("");
+            !DnaMapper::analyze_stability(strand).is_stable
+        }).count();
+
+        let mut stamp = Self::generate(seed_policy);
+        let mut unstable = unstable_tubes(&stamp);
+        for _ in 1..MAX_STABILITY_RETRIES {
+            if unstable == 0 { return Ok(stamp); }
+            stamp = Self::generate(seed_policy);
+            unstable = unstable_tubes(&stamp);
+        }
+        if unstable > 0 && !force {
+            let scope = "Provenance stamp".to_string();
+            return Err(UnstableStrands { scope, unstable, retries: MAX_STABILITY_RETRIES }.into());
+        }
+        Ok(stamp)
+    }
+
+    /// Same run, stamped for a specific tube.
+    pub fn for_tube(&self, tube: u16) -> Self {
+        Self { tube, ..self.clone() }
+    }
+
+    /// Canonical 8-4-4-4-12 hex form.
+    pub fn uuid(&self) -> String {
+        let h: String = self.archive_id.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}-{}-{}-{}-{}", &h[0..8], &h[8..12], &h[12..16], &h[16..20], &h[20..32])
+    }
+
+    /// Creation time as `YYYY-MM-DD HH:MM:SS UTC`.
+    pub fn created_utc(&self) -> String {
+        let days = (self.created_at / 86_400) as i64;
+        let secs = self.created_at % 86_400;
+
+        // Civil-from-days (Howard Hinnant), avoids pulling in a date crate.
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+                year, month, day, secs / 3600, (secs % 3600) / 60, secs % 60)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let version = self.tool_version.as_bytes();
+        let version = &version[..version.len().min(u8::MAX as usize)];
+
+        let mut out = Vec::with_capacity(32 + version.len());
+        out.extend_from_slice(MAGIC);
+        out.push(RECORD_VERSION);
+        out.extend_from_slice(&self.archive_id);
+        out.extend_from_slice(&self.created_at.to_be_bytes());
+        out.extend_from_slice(&self.tube.to_be_bytes());
+        out.push(version.len() as u8);
+        out.extend_from_slice(version);
+        out.extend_from_slice(&self.seed_policy.to_bytes());
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 32 || &bytes[0..4] != MAGIC || bytes[4] != RECORD_VERSION {
+            return None;
+        }
+        let archive_id: [u8; 16] = bytes[5..21].try_into().ok()?;
+        let created_at = u64::from_be_bytes(bytes[21..29].try_into().ok()?);
+        let tube = u16::from_be_bytes(bytes[29..31].try_into().ok()?);
+        let ver_len = bytes[31] as usize;
+        let tool_version = String::from_utf8(bytes.get(32..32 + ver_len)?.to_vec()).ok()?;
+
+        // Records without a policy byte predate configurable seeds: chained.
+        let seed_policy = match bytes.get(32 + ver_len..) {
+            Some(rest) if !rest.is_empty() => SeedPolicy::from_bytes(rest)?,
+            _ => SeedPolicy::Chained,
+        };
+
+        Some(Self { archive_id, created_at, tube, tool_version, seed_policy })
+    }
+
+    /// Builds the FASTA record for this stamp (CRC32-protected like data shards).
+    pub fn to_fasta_entry(&self, primers: (&str, &str)) -> String {
+        let record = self.to_bytes();
+        let mut protected = crc32fast::hash(&record).to_be_bytes().to_vec();
+        protected.extend_from_slice(&record);
+
+        let strand = Oligo::create_tagged(PROVENANCE_INDEX, &protected, primers);
+        format!(">{}\n{}\n", PROVENANCE_HEADER, strand)
+    }
+}
+
+/// Distinct compile runs seen in a pool, with the tubes each one was found in.
+#[derive(Debug, Default)]
+pub struct ProvenanceLedger {
+    runs: BTreeMap<[u8; 16], (Provenance, BTreeSet<u16>)>,
+}
+
+impl ProvenanceLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, p: &Provenance) {
+        self.runs.entry(p.archive_id)
+        .or_insert_with(|| (p.clone(), BTreeSet::new()))
+        .1
+        .insert(p.tube);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Number of distinct compile runs.
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn runs(&self) -> impl Iterator<Item = (&Provenance, &BTreeSet<u16>)> {
+        self.runs.values().map(|(p, tubes)| (p, tubes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dna_mapper::Base;
+
+    fn stamp(seed_policy: SeedPolicy) -> Provenance {
+        Provenance {
+            archive_id: *b"\x12\x34\x56\x78\x9a\xbc\x4d\xef\x80\x11\x22\x33\x44\x55\x66\x77",
+            created_at: 1_700_000_000,
+            tube: 3,
+            tool_version: "1.0.0".into(),
+            seed_policy,
+        }
+    }
+
+    #[test]
+    fn record_round_trips_every_seed_policy() {
+        for policy in [SeedPolicy::Chained, SeedPolicy::Fixed { address: Base::A, payload: Base::C }] {
+            let p = stamp(policy);
+            assert_eq!(Provenance::from_bytes(&p.to_bytes()), Some(p));
+        }
+    }
+
+    #[test]
+    fn legacy_record_without_policy_byte_is_chained() {
+        let mut bytes = stamp(SeedPolicy::Chained).to_bytes();
+        bytes.pop();
+        assert_eq!(Provenance::from_bytes(&bytes), Some(stamp(SeedPolicy::Chained)));
+    }
+
+    #[test]
+    fn foreign_or_truncated_records_are_rejected() {
+        let bytes = stamp(SeedPolicy::Fixed { address: Base::G, payload: Base::T }).to_bytes();
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert_eq!(Provenance::from_bytes(&magic), None);
+
+        let mut version = bytes.clone();
+        version[4] = RECORD_VERSION + 1;
+        assert_eq!(Provenance::from_bytes(&version), None);
+
+        // Cut inside the tool version string, and inside a Fixed policy.
+        assert_eq!(Provenance::from_bytes(&bytes[..34]), None);
+        assert_eq!(Provenance::from_bytes(&bytes[..bytes.len() - 1]), None);
+    }
+
+    #[test]
+    fn display_forms() {
+        let p = stamp(SeedPolicy::Chained);
+        assert_eq!(p.uuid(), "12345678-9abc-4def-8011-223344556677");
+        assert_eq!(p.created_utc(), "2023-11-14 22:13:20 UTC");
+    }
+}
@@ -1,17 +1,57 @@
 // src/rs_engine.rs
-use anyhow::{Result, anyhow};
+// REED-SOLOMON ENGINE
+// Engines are cached per (data, parity) geometry: building the coding matrix (and
+// the decode matrices the library memoizes per erasure pattern) is paid once per
+// process instead of once per block.
+//
+// RS coding is column-independent, so shards are cut into byte stripes and each
+// stripe is encoded/reconstructed on its own worker. Output is bit-identical to
+// whole-shard coding.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(not(feature = "parallel"))]
+use crate::sequential::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use anyhow::{Result, bail};
 use reed_solomon_erasure::galois_8::ReedSolomon;
 
+/// Default geometry (N data + K parity shards per block).
+pub const DEFAULT_DATA_SHARDS: usize = 10;
+pub const DEFAULT_PARITY_SHARDS: usize = 5;
+
+/// Bytes per column stripe handed to one worker.
+const STRIPE_SIZE: usize = 16 * 1024;
+
+type EngineCache = Mutex<HashMap<(usize, usize), Arc<ReedSolomon>>>;
+
+fn engine_cache() -> &'static EngineCache {
+    static CACHE: OnceLock<EngineCache> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[derive(Clone)]
 pub struct RedundancyManager {
     data_shards: usize,
     parity_shards: usize,
-    engine: ReedSolomon,
+    engine: Arc<ReedSolomon>,
 }
 
 impl RedundancyManager {
     /// Initialize the engine with a specific redundancy ratio.
+    /// Cheap after the first call for a given geometry (shared, cached engine).
     pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self> {
-        let engine = ReedSolomon::new(data_shards, parity_shards)?;
+        // A poisoned lock only means another thread panicked mid-insert; the map is still valid.
+        let mut cache = engine_cache().lock().unwrap_or_else(|e| e.into_inner());
+        let engine = match cache.get(&(data_shards, parity_shards)) {
+            Some(engine) => engine.clone(),
+            None => {
+                let engine = Arc::new(ReedSolomon::new(data_shards, parity_shards)?);
+                cache.insert((data_shards, parity_shards), engine.clone());
+                engine
+            }
+        };
         Ok(Self {
             data_shards,
             parity_shards,
@@ -19,10 +59,21 @@ impl RedundancyManager {
         })
     }
 
+    pub fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    pub fn total_shards(&self) -> usize {
+        self.data_shards + self.parity_shards
+    }
+
     /// Takes raw bytes and transforms them into a vector of equal-sized shards.
     pub fn encode_to_shards(&self, data: &[u8]) -> Result<Vec<Vec<u8>>> {
         // Calculate shard size (ceil(data_len / data_shards))
-        let shard_size = (data.len() + self.data_shards - 1) / self.data_shards;
+        let shard_size = data.len().div_ceil(self.data_shards);
+        if shard_size == 0 {
+            bail!("Cannot encode an empty block.");
+        }
 
         // Create a master buffer padded with zeros to fit the matrix
         let mut master_buffer = vec![0u8; shard_size * self.data_shards];
@@ -39,36 +90,128 @@ impl RedundancyManager {
             shards.push(vec![0u8; shard_size]);
         }
 
-        // Apply Reed-Solomon Encoding
-        self.engine.encode(&mut shards)?;
+        // Apply Reed-Solomon Encoding, one column stripe per task.
+        let (data_part, parity_part) = shards.split_at_mut(self.data_shards);
+        let data_part = &*data_part;
+        let mut stripes: Vec<(usize, Vec<&mut [u8]>)> = (0..shard_size.div_ceil(STRIPE_SIZE))
+        .map(|s| (s * STRIPE_SIZE, Vec::with_capacity(self.parity_shards)))
+        .collect();
+        for parity in parity_part.iter_mut() {
+            for (stripe, chunk) in stripes.iter_mut().zip(parity.chunks_mut(STRIPE_SIZE)) {
+                stripe.1.push(chunk);
+            }
+        }
+
+        stripes.into_par_iter().try_for_each(|(start, mut outputs)| {
+            let end = (start + STRIPE_SIZE).min(shard_size);
+            let inputs: Vec<&[u8]> = data_part.iter().map(|d| &d[start..end]).collect();
+            self.engine.encode_sep(&inputs, &mut outputs)
+        })?;
 
         Ok(shards)
     }
 
-    /// Recovery logic: Reconstructs missing shards and flattens data shards.
-    pub fn recover_file(&self, mut shards: Vec<Option<Vec<u8>>>) -> Result<Vec<u8>> {
-        // Attempt Reconstruction
-        self.engine.reconstruct(&mut shards)?;
+    /// Reconstructs every missing shard (data AND parity) in place.
+    /// Returns the complete N+K shard set, e.g. for cross-checking late arrivals.
+    pub fn reconstruct_all(&self, shards: Vec<Option<Vec<u8>>>) -> Result<Vec<Vec<u8>>> {
+        let present = shards.iter().filter(|s| s.is_some()).count();
+        if shards.len() != self.data_shards + self.parity_shards {
+            bail!("Expected {} shard slots, got {}.", self.data_shards + self.parity_shards, shards.len());
+        }
+        if present < self.data_shards {
+            bail!("Too few shards present: {} of {} required.", present, self.data_shards);
+        }
 
-        // Optimization: Pre-calculate vector capacity to avoid re-allocations.
-        // We find the first existing shard to determine the shard_size.
-        let shard_len = shards.iter()
-        .find_map(|s| s.as_ref().map(|v| v.len()))
-        .unwrap_or(0);
-
-        let capacity_hint = shard_len * self.data_shards;
-        let mut recovered = Vec::with_capacity(capacity_hint);
-
-        // Flatten Data Shards
-        for i in 0..self.data_shards {
-            if let Some(ref shard) = shards[i] {
-                recovered.extend_from_slice(shard);
-            } else {
-                return Err(anyhow!("Critical Failure: RS Engine reported success, but Shard {} is still missing.", i));
+        let shard_size = shards.iter().flatten().map(|s| s.len()).next().unwrap_or(0);
+        if shard_size == 0 || shards.iter().flatten().any(|s| s.len() != shard_size) {
+            bail!("Shards have inconsistent or zero length.");
+        }
+
+        // Missing slots become zeroed buffers flagged as absent; the engine fills them.
+        let mut full: Vec<(Vec<u8>, bool)> = shards.into_iter()
+        .map(|s| match s {
+            Some(shard) => (shard, true),
+            None => (vec![0u8; shard_size], false),
+        })
+        .collect();
+        if present == full.len() {
+            return Ok(full.into_iter().map(|(s, _)| s).collect());
+        }
+
+        let mut stripes: Vec<Vec<(&mut [u8], bool)>> = (0..shard_size.div_ceil(STRIPE_SIZE))
+        .map(|_| Vec::with_capacity(full.len()))
+        .collect();
+        for (shard, is_present) in full.iter_mut() {
+            for (stripe, chunk) in stripes.iter_mut().zip(shard.chunks_mut(STRIPE_SIZE)) {
+                stripe.push((chunk, *is_present));
             }
         }
+
+        stripes.into_par_iter().try_for_each(|mut stripe| self.engine.reconstruct(&mut stripe))?;
+
+        Ok(full.into_iter().map(|(s, _)| s).collect())
+    }
+
+    /// Flattens the data shards of a complete shard set back into the original buffer.
+    pub fn flatten_data(&self, shards: &[Vec<u8>]) -> Vec<u8> {
+        // Optimization: Pre-calculate vector capacity to avoid re-allocations.
+        let shard_len = shards.first().map(|v| v.len()).unwrap_or(0);
+        let mut recovered = Vec::with_capacity(shard_len * self.data_shards);
+
+        for shard in shards.iter().take(self.data_shards) {
+            recovered.extend_from_slice(shard);
+        }
         // NOTE: 'recovered' will contain trailing zero-padding.
         // This is expected and handled by the Zstd decoder.
-        Ok(recovered)
+        recovered
+    }
+
+    /// Recovery logic: Reconstructs missing shards and flattens data shards.
+    pub fn recover_file(&self, shards: Vec<Option<Vec<u8>>>) -> Result<Vec<u8>> {
+        let full = self.reconstruct_all(shards)?;
+        Ok(self.flatten_data(&full))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect()
+    }
+
+    #[test]
+    fn striped_coding_round_trips_across_stripe_boundaries() {
+        let rs = RedundancyManager::new(10, 5).unwrap();
+        for shard_size in [STRIPE_SIZE - 1, STRIPE_SIZE, STRIPE_SIZE + 1, 2 * STRIPE_SIZE + 5000] {
+            // Short of a full matrix, so the last data shard carries zero padding.
+            let data = block(shard_size * 10 - 7);
+            let shards = rs.encode_to_shards(&data).unwrap();
+            assert!(shards.iter().all(|s| s.len() == shard_size));
+
+            // Bit-identical to coding the whole shards in one call.
+            let mut whole = shards.clone();
+            whole[10..].iter_mut().for_each(|p| p.fill(0));
+            rs.engine.encode(&mut whole).unwrap();
+            assert_eq!(whole, shards, "shard size {}", shard_size);
+
+            // Erase K shards, data and parity alike.
+            let erased = [0, 4, 9, 10, 14];
+            let slots = shards.iter().enumerate()
+            .map(|(i, s)| (!erased.contains(&i)).then(|| s.clone()))
+            .collect();
+            let rebuilt = rs.reconstruct_all(slots).unwrap();
+            assert_eq!(rebuilt, shards, "shard size {}", shard_size);
+            assert_eq!(&rs.flatten_data(&rebuilt)[..data.len()], &data[..]);
+        }
+    }
+
+    #[test]
+    fn more_than_k_erasures_are_rejected() {
+        let rs = RedundancyManager::new(10, 5).unwrap();
+        let shards = rs.encode_to_shards(&block(STRIPE_SIZE * 10)).unwrap();
+        let slots = shards.into_iter().enumerate().map(|(i, s)| (i >= 6).then_some(s)).collect();
+        assert!(rs.recover_file(slots).is_err());
     }
 }
@@ -0,0 +1,38 @@
+// src/sequential.rs
+// SEQUENTIAL FALLBACK
+// Drop-in stand-ins for the handful of Rayon adaptors used by `parallel.rs`,
+// compiled when the `parallel` feature is disabled. They map the parallel
+// entry points onto plain iterators so the pipeline code stays identical.
+
+/// `into_par_iter()` -> `into_iter()`.
+pub trait IntoParallelIterator: IntoIterator + Sized {
+    fn into_par_iter(self) -> Self::IntoIter {
+        self.into_iter()
+    }
+}
+
+impl<I: IntoIterator> IntoParallelIterator for I {}
+
+/// `par_iter()` -> `iter()`.
+pub trait IntoParallelRefIterator<T> {
+    fn par_iter(&self) -> std::slice::Iter<'_, T>;
+}
+
+impl<T> IntoParallelRefIterator<T> for [T] {
+    fn par_iter(&self) -> std::slice::Iter<'_, T> {
+        self.iter()
+    }
+}
+
+/// `flat_map_iter()` -> `flat_map()`.
+pub trait ParallelIterator: Iterator + Sized {
+    fn flat_map_iter<U, F>(self, f: F) -> std::iter::FlatMap<Self, U, F>
+    where
+        U: IntoIterator,
+        F: FnMut(Self::Item) -> U,
+    {
+        self.flat_map(f)
+    }
+}
+
+impl<I: Iterator> ParallelIterator for I {}
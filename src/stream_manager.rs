@@ -1,5 +1,5 @@
 // src/stream_manager.rs
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Read};
 use std::mem;
 
 /// A robust, memory-aware iterator for FASTA streams.
@@ -45,10 +45,10 @@ impl<R: BufRead> Iterator for DnaBatchIterator<R> {
 
         loop {
             // Check limits BEFORE reading more to ensure we stay within RAM bounds
-            if !batch.is_empty() {
-                if batch.len() >= self.max_items || current_batch_bytes >= self.max_bytes {
-                    return Some(Ok(batch));
-                }
+            if !batch.is_empty()
+                && (batch.len() >= self.max_items || current_batch_bytes >= self.max_bytes)
+            {
+                return Some(Ok(batch));
             }
 
             match self.lines.next() {
@@ -100,3 +100,19 @@ impl<R: BufRead> Iterator for DnaBatchIterator<R> {
         }
     }
 }
+
+/// Reads until `buf` is full or EOF is reached. Returns the number of bytes read.
+/// A plain `read` may return short counts, which would misalign a lockstep comparison
+/// (or the exact block count recorded in the manifest).
+pub fn fill_buffer<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
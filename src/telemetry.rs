@@ -0,0 +1,144 @@
+// src/telemetry.rs
+// DECODE TELEMETRY
+// Structured accounting of what happened to every strand during restore.
+// Instead of collapsing every failure into `None`, the parser reports *why*
+// a strand was accepted or rejected so users can tell "the tube is mostly
+// noise" apart from "the primers are wrong" or "mutation rate is too high".
+
+use crate::provenance::Provenance;
+
+/// The fate of a single strand after passing through the Viterbi pipeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeOutcome {
+    /// Strand decoded and passed CRC32.
+    /// The flags record which stages needed the Viterbi fallback.
+    Decoded {
+        block_id: u32,
+        index: usize,
+        data: Vec<u8>,
+        address_healed: bool,
+        payload_healed: bool,
+        /// A primer or the core needed the indel-tolerant (edit distance) decoder.
+        indel_healed: bool,
+    },
+    /// Header is not a Helix `>blkN_sM ...` record (see `fasta_header`).
+    MalformedHeader,
+    /// Primers exceeded the fuzzy mismatch tolerance (foreign strand or heavy rot).
+    PrimerMismatch,
+    /// Address region could not be decoded, even after Viterbi healing.
+    AddressFailure,
+    /// Payload decoded but CRC32 never matched (direct or healed).
+    CrcFailure,
+    /// Strand verified, but its (healed) address points outside the RS geometry.
+    /// Usually a Viterbi heal that converged on the wrong path; never trusted.
+    IndexOutOfRange { block_id: u32, index: usize },
+    /// A verified archive provenance stamp (not a data shard).
+    Provenance(Provenance),
+    /// A verified shard of the archive manifest (`index` within the manifest's own RS set).
+    Manifest { index: usize, data: Vec<u8> },
+}
+
+impl DecodeOutcome {
+    /// Rejects decoded shards whose index cannot exist in a `total_shards` (N+K) block.
+    pub fn with_shard_limit(self, total_shards: usize) -> Self {
+        match self {
+            DecodeOutcome::Decoded { block_id, index, .. } if index >= total_shards => {
+                DecodeOutcome::IndexOutOfRange { block_id, index }
+            }
+            other => other,
+        }
+    }
+
+    /// Collapses the outcome into the legacy `(block_id, index, data)` tuple.
+    pub fn into_shard(self) -> Option<(u32, usize, Vec<u8>)> {
+        match self {
+            DecodeOutcome::Decoded { block_id, index, data, .. } => Some((block_id, index, data)),
+            _ => None,
+        }
+    }
+}
+
+/// Aggregated decode counters for a restore run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeTelemetry {
+    /// Strands that yielded a verified shard (by any path).
+    pub decoded: u64,
+    /// Strands decoded without touching the Viterbi engine.
+    pub fast_path: u64,
+    /// Strands whose address required Viterbi healing.
+    pub address_healed: u64,
+    /// Strands whose payload required Viterbi healing.
+    pub payload_healed: u64,
+    /// Strands recovered from insertions/deletions (shifted frame).
+    pub indel_healed: u64,
+    /// Strands rejected by CRC32 after all decode attempts.
+    pub crc_failures: u64,
+    /// Strands rejected at the primer gate.
+    pub primer_mismatches: u64,
+    /// Strands whose address region was unrecoverable.
+    pub address_failures: u64,
+    /// Records that were not Helix strands at all.
+    pub malformed_headers: u64,
+    /// Strands that passed CRC32 but carried an impossible shard index.
+    pub index_out_of_range: u64,
+    /// Verified provenance stamps.
+    pub provenance: u64,
+    /// Verified manifest shards.
+    pub manifest: u64,
+}
+
+impl DecodeTelemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accounts for a single strand outcome.
+    pub fn record(&mut self, outcome: &DecodeOutcome) {
+        match outcome {
+            DecodeOutcome::Decoded { address_healed, payload_healed, indel_healed, .. } => {
+                self.decoded += 1;
+                if !address_healed && !payload_healed && !indel_healed { self.fast_path += 1; }
+                if *address_healed { self.address_healed += 1; }
+                if *payload_healed { self.payload_healed += 1; }
+                if *indel_healed { self.indel_healed += 1; }
+            }
+            DecodeOutcome::MalformedHeader => self.malformed_headers += 1,
+            DecodeOutcome::PrimerMismatch => self.primer_mismatches += 1,
+            DecodeOutcome::AddressFailure => self.address_failures += 1,
+            DecodeOutcome::CrcFailure => self.crc_failures += 1,
+            DecodeOutcome::IndexOutOfRange { .. } => self.index_out_of_range += 1,
+            DecodeOutcome::Provenance(_) => self.provenance += 1,
+            DecodeOutcome::Manifest { .. } => self.manifest += 1,
+        }
+    }
+
+    /// Folds another set of counters into this one (e.g. per-batch -> global).
+    pub fn merge(&mut self, other: &DecodeTelemetry) {
+        self.decoded += other.decoded;
+        self.fast_path += other.fast_path;
+        self.address_healed += other.address_healed;
+        self.payload_healed += other.payload_healed;
+        self.indel_healed += other.indel_healed;
+        self.crc_failures += other.crc_failures;
+        self.primer_mismatches += other.primer_mismatches;
+        self.address_failures += other.address_failures;
+        self.malformed_headers += other.malformed_headers;
+        self.index_out_of_range += other.index_out_of_range;
+        self.provenance += other.provenance;
+        self.manifest += other.manifest;
+    }
+
+    /// Number of strands that yielded nothing.
+    pub fn rejected(&self) -> u64 {
+        self.crc_failures
+            + self.primer_mismatches
+            + self.address_failures
+            + self.malformed_headers
+            + self.index_out_of_range
+    }
+
+    /// Total strands inspected.
+    pub fn total(&self) -> u64 {
+        self.decoded + self.provenance + self.manifest + self.rejected()
+    }
+}
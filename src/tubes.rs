@@ -0,0 +1,113 @@
+// src/tubes.rs
+// MULTI-TUBE POOL PARTITIONING
+// Physical archives are rarely one tube. Blocks are split into contiguous ranges,
+// one range per tube, each tube amplified by its own orthogonal primer pair.
+// The map file records which tube holds which blocks so restore can reassemble.
+//
+// Contiguous ranges (rather than round-robin) keep restore memory flat: feeding
+// tubes in order means blocks still arrive in order.
+
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context, anyhow, bail};
+use serde::{Serialize, Deserialize};
+use crate::manifest::Codec;
+use crate::oligo::SeedPolicy;
+
+/// One physical container and the blocks it holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TubeEntry {
+    pub tube: usize,
+    pub file: String,
+    pub primer_fwd: String,
+    pub primer_rev: String,
+    pub blocks: Vec<u32>,
+}
+
+/// The tube map written next to a multi-tube archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TubeMap {
+    /// UUID of the compile run (matches the provenance strands in every tube).
+    #[serde(default)]
+    pub archive_id: String,
+    pub tag: String,
+    /// Block erasure code (maps written before fountain support are Reed-Solomon).
+    #[serde(default)]
+    pub codec: Codec,
+    pub data_shards: usize,
+    pub parity_shards: usize,
+    /// Trellis seed policy (`chained` or `fixed:XY`) of the data strands.
+    #[serde(default = "default_seed_policy")]
+    pub seed_policy: String,
+    pub tubes: Vec<TubeEntry>,
+}
+
+fn default_seed_policy() -> String {
+    SeedPolicy::Chained.to_string()
+}
+
+impl TubeMap {
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path).context(format!("Failed to read tube map: {}", path))?;
+        let map: TubeMap = serde_json::from_str(&text).context(format!("Invalid tube map: {}", path))?;
+        if map.tubes.is_empty() {
+            bail!("Tube map {} lists no tubes.", path);
+        }
+        Ok(map)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(path, text).context(format!("Failed to write tube map: {}", path))
+    }
+
+    /// Parsed trellis seed policy.
+    pub fn seeds(&self) -> Result<SeedPolicy> {
+        self.seed_policy.parse().map_err(|e: String| anyhow!(e))
+    }
+
+    /// Tubes owning blocks that are absent from `recovered`, with the missing IDs.
+    pub fn missing_blocks<F: Fn(u32) -> bool>(&self, recovered: F) -> Vec<(&TubeEntry, Vec<u32>)> {
+        self.tubes.iter()
+        .map(|t| (t, t.blocks.iter().copied().filter(|b| !recovered(*b)).collect::<Vec<_>>()))
+        .filter(|(_, missing)| !missing.is_empty())
+        .collect()
+    }
+
+    /// Primer pairs of every tube, in tube order.
+    pub fn primer_sets(&self) -> Vec<(String, String)> {
+        self.tubes.iter().map(|t| (t.primer_fwd.clone(), t.primer_rev.clone())).collect()
+    }
+}
+
+/// Tube holding `block_id` out of an estimated `total_blocks`.
+/// Ranges are balanced (sizes differ by at most one). Clamped to the last tube in
+/// case the input grew (or reads came back short) after the estimate was taken.
+pub fn tube_for_block(block_id: u32, total_blocks: u64, tubes: usize) -> usize {
+    if total_blocks == 0 { return 0; }
+    ((block_id as u64 * tubes as u64 / total_blocks) as usize).min(tubes - 1)
+}
+
+/// Derives the FASTA path of tube `i`: `archive.fasta` -> `archive.tube{i}.fasta`.
+pub fn tube_path(output: &str, tube: usize) -> String {
+    let path = Path::new(output);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(ext)) => path
+        .with_file_name(format!("{}.tube{}.{}", stem.to_string_lossy(), tube, ext.to_string_lossy()))
+        .to_string_lossy()
+        .into_owned(),
+        _ => format!("{}.tube{}", output, tube),
+    }
+}
+
+/// Derives the map path: `archive.fasta` -> `archive.tubes.json`.
+pub fn map_path(output: &str) -> String {
+    let path = Path::new(output);
+    match path.file_stem() {
+        Some(stem) => path
+        .with_file_name(format!("{}.tubes.json", stem.to_string_lossy()))
+        .to_string_lossy()
+        .into_owned(),
+        None => format!("{}.tubes.json", output),
+    }
+}
@@ -368,6 +368,29 @@ def test_resilience_dropout(sandbox):
 
     return UI.fail_check("Recovery failed on safe dropout")
 
+def test_fountain_dropout(sandbox):
+    UI.section("Resilience: Fountain Codec under Dropout")
+
+    src = os.path.join(sandbox, "ftn.bin")
+    dst = os.path.join(sandbox, "ftn.fasta")
+    dec = os.path.join(sandbox, "ftn_decay.fasta")
+    rec = os.path.join(sandbox, "ftn_rec.bin")
+
+    with open(src, "wb") as f: f.write(os.urandom(20000))
+    h_orig = get_hash(src)
+
+    ok, _, err = run_cmd(["compile", src, "--output", dst, "--codec", "fountain", "--data", 100, "--overhead", "2x"])
+    if not ok: return UI.fail_check("Fountain compilation failed", err)
+    run_cmd(["simulate", dst, "--output", dec, "--dropout", 25])
+    ok, _, err = run_cmd(["restore", dec, rec, "--codec", "fountain", "--data", 100, "--parity", 100])
+    if not ok: return UI.fail_check("Fountain restoration failed", err)
+
+    if get_hash(rec) == h_orig:
+        UI.pass_check("LT droplets recovered missing strands")
+        return True
+
+    return UI.fail_check("Hash mismatch after fountain recovery")
+
 def test_resilience_corruption(sandbox):
     UI.section("Resilience: Chemical Corruption (Bit-Rot)")
 
@@ -394,6 +417,40 @@ def test_resilience_corruption(sandbox):
 
     return UI.fail_check("Failed to recover from bit-rot")
 
+def test_consensus_coverage(sandbox):
+    UI.section("Advanced Resilience: Read Consensus & Coverage Gating")
+
+    src = os.path.join(sandbox, "reads.bin")
+    dst = os.path.join(sandbox, "reads.fasta")
+    dec = os.path.join(sandbox, "reads_noisy.fasta")
+    rec = os.path.join(sandbox, "reads_rec.bin")
+
+    with open(src, "wb") as f: f.write(os.urandom(2000))
+    h_orig = get_hash(src)
+
+    ok, _, err = run_cmd(["compile", src, "--output", dst, "--copies", 6])
+    if not ok: return UI.fail_check("Compilation with copies failed", err)
+
+    UI.info("Simulating 1% mutation: single reads fail CRC, 6 reads per strand vote...")
+    ok, _, err = run_cmd(["simulate", dst, "--output", dec, "--dropout", "0", "--mutation", "0.01"])
+    if not ok: return UI.fail_check("Simulation failed", err)
+
+    ok, out, err = run_cmd(["restore", dec, rec])
+    if not ok: return UI.fail_check("Consensus restoration failed", err)
+    if get_hash(rec) != h_orig: return UI.fail_check("Hash mismatch after consensus")
+    if "(0 shards rescued)" in out: return UI.fail_check("Restore succeeded without consensus", out)
+    UI.pass_check("Noisy reads voted into verified shards")
+
+    UI.info("Requiring more reads per strand than were synthesized (Should Fail)...")
+    ok, out, _ = run_cmd(["restore", dst, os.path.join(sandbox, "gated.bin"), "--min-coverage", 7])
+    if ok: return UI.fail_check("Shards were used below --min-coverage", out)
+
+    ok, _, err = run_cmd(["restore", dst, rec, "--min-coverage", 6])
+    if not ok or get_hash(rec) != h_orig: return UI.fail_check("Restore at full coverage failed", err)
+
+    UI.pass_check("--min-coverage gates shards by read count")
+    return True
+
 def test_viterbi_correction(sandbox):
     UI.section("Advanced Resilience: Viterbi Error Correction")
 
@@ -607,8 +664,10 @@ def main():
         test_primer_collision_safety,
         test_soup_contamination,
         test_resilience_dropout,
+        test_fountain_dropout,
         test_resilience_corruption,
         test_viterbi_correction,
+        test_consensus_coverage,
         test_stability_retry_logic,
         test_parameter_mismatch,
         test_catastrophic_failure,